@@ -0,0 +1,265 @@
+use std::f32::consts::PI;
+
+use raylib::prelude::Vector2;
+
+use crate::caster::trace_ray;
+use crate::maze::Maze;
+use crate::maze::path::{self, GridPos};
+use crate::rng::Rng;
+
+/// How an enemy type sees and moves, see `enemies::ENEMY_TYPES`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AiParams {
+    /// How far the enemy can see, in blocks
+    pub view_distance: f32,
+    /// Field of view, in radians
+    pub fov: f32,
+    /// Walking speed on patrol, in blocks per second
+    pub speed: f32,
+    /// Speed while chasing or searching, in blocks per second
+    pub chase_speed: f32,
+    /// Seconds spent heading for where the player was last seen before giving up
+    pub search_time: f32,
+    /// How far a patrol may lead, in grid steps
+    pub patrol_range: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AiState {
+    /// Standing still for a moment
+    Idle { timer: f32 },
+    /// Walking to a random cell nearby
+    Patrol,
+    /// Following the player it can see, `last_seen` is the player's cell on
+    /// the latest frame it did
+    Chase { last_seen: GridPos },
+    /// Going to where the player was last seen, then back to patrolling
+    Search { timer: f32, last_seen: GridPos },
+}
+
+/// Per-enemy AI state.
+#[derive(Debug, Clone)]
+pub struct Brain {
+    pub state: AiState,
+    pub params: AiParams,
+    /// Where the enemy is looking, radians
+    pub facing: f32,
+    /// Cells still to walk, the next one last
+    pub path: Vec<GridPos>,
+    repath_timer: f32,
+}
+
+impl Brain {
+    pub fn new(params: AiParams) -> Self {
+        Brain {
+            state: AiState::Idle { timer: 0.5 },
+            params,
+            facing: 0.0,
+            path: Vec::new(),
+            repath_timer: 0.0,
+        }
+    }
+}
+
+/// Seconds between path updates while chasing.
+const REPATH_INTERVAL: f32 = 0.4;
+
+fn cell_of(pos: Vector2, block_size: f32) -> GridPos {
+    ((pos.x / block_size).max(0.0) as usize, (pos.y / block_size).max(0.0) as usize)
+}
+
+fn center((i, j): GridPos, block_size: f32) -> Vector2 {
+    Vector2::new((i as f32 + 0.5) * block_size, (j as f32 + 0.5) * block_size)
+}
+
+fn enemy_walkable(maze: &Maze, (i, j): GridPos) -> bool {
+    maze.get(i, j).is_some_and(|cell| cell.is_enemy_walkable())
+}
+
+/// True if `target` is within view distance and field of view, with no wall in
+/// between along a ray cast through the maze. A chasing enemy keeps track of
+/// the player all around, not just in front.
+pub fn can_see(brain: &Brain, pos: Vector2, target: Vector2, maze: &Maze, block_size: f32) -> bool {
+    let to_target = target - pos;
+    let distance = to_target.length();
+    if distance > brain.params.view_distance * block_size {
+        return false;
+    }
+    let angle = to_target.y.atan2(to_target.x);
+    if !matches!(brain.state, AiState::Chase { .. }) {
+        let mut diff = angle - brain.facing;
+        while diff > PI {
+            diff -= 2.0 * PI;
+        }
+        while diff < -PI {
+            diff += 2.0 * PI;
+        }
+        if diff.abs() > brain.params.fov / 2.0 {
+            return false;
+        }
+    }
+    trace_ray(maze, pos, angle, block_size).distance >= distance
+}
+
+/// A* path from the enemy to `target`. A target enemies can't stand on (like the
+/// start cell) is approached up to the cell next to it.
+fn path_to(brain: &mut Brain, pos: Vector2, target: GridPos, maze: &Maze, block_size: f32) {
+    let from = cell_of(pos, block_size);
+    let path = path::astar(maze, from, target, |p| p == target || enemy_walkable(maze, p));
+    brain.path = match path {
+        Some(mut path) => {
+            if !enemy_walkable(maze, target) {
+                path.pop();
+            }
+            // the first cell is the one we stand in: skip its center unless that
+            // would mean cutting diagonally past a wall corner to the next one
+            if path.len() > 1 {
+                let offset = pos - center(from, block_size);
+                let sideways = if path[1].0 != from.0 { offset.y } else { offset.x };
+                if sideways.abs() < block_size * 0.05 {
+                    path.remove(0);
+                }
+            }
+            path.reverse();
+            path
+        }
+        None => Vec::new(),
+    };
+}
+
+fn start_patrol(brain: &mut Brain, pos: Vector2, maze: &Maze, block_size: f32, rng: &mut Rng) {
+    let from = cell_of(pos, block_size);
+    let dist = path::distances_from(maze, from, |p| enemy_walkable(maze, p));
+    let range = 2..=brain.params.patrol_range.max(2);
+    let candidates: Vec<GridPos> = dist
+        .iter()
+        .enumerate()
+        .flat_map(|(j, row)| row.iter().enumerate().filter_map(move |(i, d)| d.map(|d| ((i, j), d))))
+        .filter(|(_, d)| range.contains(d))
+        .map(|(cell, _)| cell)
+        .collect();
+
+    if candidates.is_empty() {
+        brain.state = AiState::Idle { timer: 1.0 };
+        return;
+    }
+    let target = candidates[rng.below(candidates.len())];
+    path_to(brain, pos, target, maze, block_size);
+    brain.state = AiState::Patrol;
+}
+
+/// Walks along the path, returns false once there is nothing left to walk.
+fn follow_path(brain: &mut Brain, pos: &mut Vector2, speed: f32, block_size: f32, dt: f32) -> bool {
+    let Some(&next) = brain.path.last() else {
+        return false;
+    };
+    let target = center(next, block_size);
+    let delta = target - *pos;
+    let distance = delta.length();
+    let step = speed * block_size * dt;
+
+    if distance > 0.0 {
+        brain.facing = delta.y.atan2(delta.x);
+    }
+    if distance <= step {
+        *pos = target;
+        brain.path.pop();
+    } else {
+        *pos += delta * (step / distance);
+    }
+    true
+}
+
+/// Advances one enemy by `dt` seconds: looks for the player, switches state and walks.
+pub fn update(brain: &mut Brain, pos: &mut Vector2, player_pos: Vector2, maze: &Maze, block_size: f32, dt: f32, rng: &mut Rng) {
+    let sees = can_see(brain, *pos, player_pos, maze, block_size);
+    let player_cell = cell_of(player_pos, block_size);
+
+    if sees {
+        if !matches!(brain.state, AiState::Chase { .. }) {
+            brain.repath_timer = 0.0;
+        }
+        brain.state = AiState::Chase { last_seen: player_cell };
+    }
+
+    match brain.state {
+        AiState::Idle { timer } => {
+            if timer - dt <= 0.0 {
+                start_patrol(brain, *pos, maze, block_size, rng);
+            } else {
+                brain.state = AiState::Idle { timer: timer - dt };
+            }
+        }
+        AiState::Patrol => {
+            if !follow_path(brain, pos, brain.params.speed, block_size, dt) {
+                brain.state = AiState::Idle { timer: 0.5 + rng.next_f32() * 1.5 };
+            }
+        }
+        AiState::Chase { last_seen } => {
+            if sees {
+                brain.repath_timer -= dt;
+                if brain.repath_timer <= 0.0 || brain.path.is_empty() {
+                    path_to(brain, *pos, player_cell, maze, block_size);
+                    brain.repath_timer = REPATH_INTERVAL;
+                }
+            } else {
+                // lost sight: head for the last place the player was seen, not
+                // where they are now
+                path_to(brain, *pos, last_seen, maze, block_size);
+                brain.state = AiState::Search { timer: brain.params.search_time, last_seen };
+            }
+            follow_path(brain, pos, brain.params.chase_speed, block_size, dt);
+        }
+        AiState::Search { timer, last_seen } => {
+            // arrived, or took too long getting there
+            if !follow_path(brain, pos, brain.params.chase_speed, block_size, dt) || timer - dt <= 0.0 {
+                start_patrol(brain, *pos, maze, block_size, rng);
+            } else {
+                brain.state = AiState::Search { timer: timer - dt, last_seen };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: f32 = 100.0;
+
+    fn params() -> AiParams {
+        AiParams { view_distance: 10.0, fov: PI / 2.0, speed: 1.0, chase_speed: 2.0, search_time: 10.0, patrol_range: 8 }
+    }
+
+    fn at((i, j): GridPos) -> Vector2 {
+        center((i, j), BLOCK)
+    }
+
+    #[test]
+    fn a_lost_player_is_searched_for_where_last_seen() {
+        // a corridor along the top that turns down at the east end
+        let rows = ["+-----+", "|     |", "+---+ |", "|   | |", "+---+-+"];
+        let maze = Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect());
+        let mut rng = Rng::new(1);
+        let mut brain = Brain::new(params());
+        let mut pos = at((1, 1));
+
+        update(&mut brain, &mut pos, at((4, 1)), &maze, BLOCK, 0.01, &mut rng);
+        assert_eq!(brain.state, AiState::Chase { last_seen: (4, 1) });
+
+        // the player slips round the corner, out of sight
+        let hidden = at((5, 3));
+        update(&mut brain, &mut pos, hidden, &maze, BLOCK, 0.01, &mut rng);
+        assert!(matches!(brain.state, AiState::Search { last_seen: (4, 1), .. }), "{:?}", brain.state);
+        assert_eq!(brain.path.first(), Some(&(4, 1)));
+
+        for _ in 0..100 {
+            if !matches!(brain.state, AiState::Search { .. }) {
+                break;
+            }
+            update(&mut brain, &mut pos, hidden, &maze, BLOCK, 0.05, &mut rng);
+        }
+        assert_eq!(brain.state, AiState::Patrol);
+        assert_eq!(cell_of(pos, BLOCK), (4, 1));
+    }
+}
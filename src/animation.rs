@@ -0,0 +1,150 @@
+use std::f32::consts::PI;
+
+/// One image of an animation: a texture key plus how to turn it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// Key into the TextureManager
+    pub key: char,
+    /// Frame of a sprite sheet, see `TextureManager::sample`
+    pub index: u16,
+    /// Quarter turns counter-clockwise
+    pub turns: u8,
+    /// Mirrored left to right, e.g. to reuse a left side view as the right one
+    pub flip: bool,
+}
+
+impl Frame {
+    pub const fn new(key: char) -> Self {
+        Frame { key, index: 0, turns: 0, flip: false }
+    }
+
+    pub const fn at(self, index: u16) -> Self {
+        Frame { index, ..self }
+    }
+
+    pub const fn turned(self, turns: u8) -> Self {
+        Frame { turns: turns % 4, ..self }
+    }
+
+    pub const fn flipped(self) -> Self {
+        Frame { flip: !self.flip, ..self }
+    }
+
+    /// Maps a position on the drawn sprite, `u` and `v` in [0, 1), to the
+    /// position to sample in the texture.
+    pub fn sample(&self, u: f32, v: f32) -> (f32, f32) {
+        let u = if self.flip { 1.0 - u } else { u };
+        match self.turns {
+            1 => (1.0 - v, u),
+            2 => (1.0 - u, 1.0 - v),
+            3 => (v, 1.0 - u),
+            _ => (u, v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimName {
+    Idle,
+    Walk,
+    Attack,
+    Hurt,
+}
+
+/// A list of frames played at a fixed rate. Each frame holds one image per
+/// direction the entity can be seen from: the first is its front, the rest go
+/// clockwise around it as seen from above. One image means the same from everywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub frames: &'static [&'static [Frame]],
+    /// Frames per second
+    pub fps: f32,
+    /// Idle and walk loop; attack and hurt play once
+    pub looping: bool,
+}
+
+impl Animation {
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 / self.fps
+    }
+
+    /// Image to show `time` seconds in, seen from `view_angle` radians around
+    /// the entity (0 is straight in front of it).
+    pub fn frame(&self, time: f32, view_angle: f32) -> Option<Frame> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let index = (time.max(0.0) * self.fps) as usize;
+        let index = if self.looping { index % self.frames.len() } else { index.min(self.frames.len() - 1) };
+        let directions = self.frames[index];
+        if directions.is_empty() {
+            return None;
+        }
+        let step = 2.0 * PI / directions.len() as f32;
+        let direction = (view_angle.rem_euclid(2.0 * PI) / step + 0.5) as usize % directions.len();
+        Some(directions[direction])
+    }
+}
+
+/// The named animations of one kind of entity. An empty one falls back to idle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationSet {
+    pub idle: Animation,
+    pub walk: Animation,
+    pub attack: Animation,
+    pub hurt: Animation,
+}
+
+impl AnimationSet {
+    pub fn get(&self, name: AnimName) -> &Animation {
+        let animation = match name {
+            AnimName::Idle => &self.idle,
+            AnimName::Walk => &self.walk,
+            AnimName::Attack => &self.attack,
+            AnimName::Hurt => &self.hurt,
+        };
+        if animation.frames.is_empty() { &self.idle } else { animation }
+    }
+}
+
+/// Which animation an entity plays and how far into it it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animator {
+    pub current: AnimName,
+    /// Seconds since `current` started
+    pub time: f32,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Animator { current: AnimName::Idle, time: 0.0 }
+    }
+
+    /// Switches to `name`, starting it over unless it is already playing.
+    pub fn play(&mut self, name: AnimName) {
+        if self.current != name {
+            self.current = name;
+            self.time = 0.0;
+        }
+    }
+
+    /// Advances by `dt` seconds. Looping animations give way to `base` (idle or
+    /// walk) right away, one-shot ones once they have played through.
+    pub fn update(&mut self, set: &AnimationSet, base: AnimName, dt: f32) {
+        self.time += dt;
+        let animation = set.get(self.current);
+        if animation.looping || self.time >= animation.duration() {
+            self.play(base);
+        }
+    }
+
+    pub fn frame(&self, set: &AnimationSet, view_angle: f32) -> Option<Frame> {
+        set.get(self.current).frame(self.time, view_angle)
+    }
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Animator::new()
+    }
+}
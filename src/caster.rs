@@ -1,15 +1,55 @@
 use raylib::color::Color;
+use raylib::prelude::Vector2;
 
 use crate::framebuffer::{self, Framebuffer};
+use crate::line::line;
 use crate::player::Player;
-use crate::maze::Maze;
+use crate::maze::{Cell, Maze};
+
+/// Face of the wall cell that a ray hit. Rows grow downwards, so a ray
+/// travelling south (+y) hits the `North` face of the next wall cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Side {
+    /// True for the faces hit while stepping across a vertical grid line.
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Side::East | Side::West)
+    }
+}
 
 pub struct Intersect {
     pub distance: f32,
-    pub impact: char,
+    pub impact: Cell,
     pub tx: usize,
+    /// Exact hit position along the face, in [0, 1), left to right as seen by the viewer.
+    pub wall_x: f32,
+    pub side: Side,
+    pub cell_x: usize,
+    pub cell_y: usize,
 }
 
+impl Intersect {
+    fn miss(max_distance: f32, cell_x: isize, cell_y: isize) -> Self {
+        Intersect {
+            distance: max_distance,
+            impact: Cell::Empty,
+            tx: 0,
+            wall_x: 0.0,
+            side: Side::North,
+            cell_x: cell_x.max(0) as usize,
+            cell_y: cell_y.max(0) as usize,
+        }
+    }
+}
+
+/// Draws the ray for the top-down debug view and returns the first wall it hits,
+/// see `trace_ray`.
 pub fn cast_ray(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
@@ -18,80 +58,299 @@ pub fn cast_ray(
     block_size: usize,
     draw: bool,
 ) -> Intersect {
-    let mut d = 0.1;
-    let max_distance = block_size as f32 *200.0;
+    let intersect = trace_ray(maze, player.pos, a, block_size as f32);
 
-    // Precompute maze dimensions in world units
-    let maze_rows = maze.len();
-    let maze_cols = maze[0].len();
-    let world_width  = (maze_cols * block_size) as isize;
-    let world_height = (maze_rows * block_size) as isize;
+    if draw && intersect.impact != Cell::Empty {
+        framebuffer.set_current_color(Color::WHITE);
+        let hit = Vector2::new(player.pos.x + a.cos() * intersect.distance, player.pos.y + a.sin() * intersect.distance);
+        line(framebuffer, player.pos, hit);
+    }
+    intersect
+}
 
-    framebuffer.set_current_color(Color::WHITE);
+/// Walks the grid cell by cell (DDA) from `origin` (world coordinates) along
+/// angle `a` and returns the first opaque cell hit. Needs no framebuffer, so
+/// game logic can use it for line-of-sight checks.
+///
+/// A sliding door that is partly open lets rays through the gap, see
+/// `Maze::door_extent` and `Maze::door_along_rows`.
+pub fn trace_ray(maze: &Maze, origin: Vector2, a: f32, block_size: f32) -> Intersect {
+    let max_distance = block_size * 200.0;
+    let bs = block_size;
 
-    loop {
-        let cos = d * a.cos();
-        let sin = d * a.sin();
+    let dir_x = a.cos();
+    let dir_y = a.sin();
 
-        let wx = player.pos.x + cos;
-        let wy = player.pos.y + sin;
+    // Work in grid units; the ray direction is a unit vector so the side
+    // distances below are also distances along the ray.
+    let px = origin.x / bs;
+    let py = origin.y / bs;
+    let mut map_x = px.floor() as isize;
+    let mut map_y = py.floor() as isize;
 
-        // if we go outside the world bounds, stop and return "no hit" far away
-        if wx < 0.0 || wy < 0.0 || wx >= world_width as f32 || wy >= world_height as f32 {
-            return Intersect {
-                distance: max_distance,
-                impact: ' ', // or some sentinel
-                tx: 0,
-            };
-        }
+    let delta_x = if dir_x == 0.0 { f32::INFINITY } else { (1.0 / dir_x).abs() };
+    let delta_y = if dir_y == 0.0 { f32::INFINITY } else { (1.0 / dir_y).abs() };
 
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
+    let (step_x, mut side_dist_x) = if dir_x < 0.0 {
+        (-1, (px - map_x as f32) * delta_x)
+    } else {
+        (1, (map_x as f32 + 1.0 - px) * delta_x)
+    };
+    let (step_y, mut side_dist_y) = if dir_y < 0.0 {
+        (-1, (py - map_y as f32) * delta_y)
+    } else {
+        (1, (map_y as f32 + 1.0 - py) * delta_y)
+    };
 
-        let i = x / block_size;
-        let j = y / block_size;
+    loop {
+        let crossed_vertical = side_dist_x < side_dist_y;
+        let t = if crossed_vertical {
+            map_x += step_x;
+            side_dist_x += delta_x;
+            side_dist_x - delta_x
+        } else {
+            map_y += step_y;
+            side_dist_y += delta_y;
+            side_dist_y - delta_y
+        };
 
-        // extra safety: check maze indices
-        if j >= maze_rows || i >= maze_cols {
-            return Intersect {
-                distance: max_distance,
-                impact: ' ',
-                tx: 0,
-            };
+        // if we go outside the world (or past a short row), stop and return "no hit" far away
+        if map_x < 0 || map_y < 0 || t * bs > max_distance {
+            return Intersect::miss(max_distance, map_x, map_y);
         }
+        let (i, j) = (map_x as usize, map_y as usize);
+        let Some(cell) = maze.get(i, j) else {
+            return Intersect::miss(max_distance, map_x, map_y);
+        };
 
-        if maze[j][i] != ' ' && maze[j][i] != 's' {
-            let hitx = x - i * block_size;
-            let hity = y - j * block_size;
-            let mut maxhit = hity;
-            
-            if 1 < hitx && hitx < block_size - 1 {
-                maxhit = hitx;
+        if cell.is_opaque() {
+            let (side, wall_x) = if crossed_vertical {
+                let frac = (py + t * dir_y).fract();
+                if step_x > 0 { (Side::West, frac) } else { (Side::East, 1.0 - frac) }
+            } else {
+                let frac = (px + t * dir_x).fract();
+                if step_y > 0 { (Side::North, 1.0 - frac) } else { (Side::South, frac) }
+            };
+            let mut wall_x = wall_x.clamp(0.0, 0.999_999);
+
+            // the gap is measured in world space across the whole door, so the
+            // panel slides the same way seen from either side and a wide door
+            // moves as one piece: it opens from its low end (north or west).
+            // Which way the door runs comes from its neighbours, not from the
+            // face the ray crossed, so rays inside a doorway see it right too
+            let mut t = t;
+            let openness = maze.door_openness(i, j);
+            if cell.is_door() && openness > 0.0 {
+                let along_rows = maze.door_along_rows(i, j);
+                let (first, len) = maze.door_extent(i, j, along_rows);
+                let open_len = openness * len as f32;
+                let gap_end = first as f32 + open_len;
+                let along = if along_rows { py + t * dir_y } else { px + t * dir_x };
+                if crossed_vertical == along_rows {
+                    // in through a long side of the door
+                    if along < gap_end {
+                        continue;
+                    }
+                    // the panel's texture moves with it
+                    let shifted = (along - open_len).rem_euclid(1.0);
+                    let mirrored = if crossed_vertical { step_x < 0 } else { step_y > 0 };
+                    wall_x = if mirrored { 1.0 - shifted } else { shifted }.clamp(0.0, 0.999_999);
+                } else {
+                    // in through an end, running along the door: the panel's edge
+                    // is where the gap ends, if that is inside this cell
+                    let index = if along_rows { j } else { i } as f32;
+                    let dir_along = if along_rows { dir_y } else { dir_x };
+                    if gap_end >= index + 1.0 {
+                        continue;
+                    }
+                    if dir_along > 0.0 && gap_end > along {
+                        let edge = t + (gap_end - along) / dir_along;
+                        // leaves sideways before reaching it
+                        if edge > if crossed_vertical { side_dist_y } else { side_dist_x } {
+                            continue;
+                        }
+                        t = edge;
+                        let frac = if along_rows { px + t * dir_x } else { py + t * dir_y }.fract();
+                        wall_x = if along_rows { 1.0 - frac } else { frac }.clamp(0.0, 0.999_999);
+                    }
+                }
             }
 
-            let tx = ((maxhit as f32 * 128.0) / block_size as f32) as usize;
-            
-            return Intersect{
-                distance: d,
-                impact: maze[j][i],
-                tx: tx,
+            return Intersect {
+                distance: t * bs,
+                impact: cell,
+                tx: (wall_x * 128.0) as usize,
+                wall_x,
+                side,
+                cell_x: i,
+                cell_y: j,
             };
         }
-        
+    }
+}
 
-        if draw {
-            framebuffer.set_pixel(x as u32, y as u32, d);
-        }
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
 
-        d += 1.0;
+    use super::*;
 
-        // safety net: in case we somehow never hit a wall or leave the map
-        if d > max_distance {
-            return Intersect {
-                distance: max_distance,
-                impact: ' ',
-                tx: 0,
-            };
-        }
+    const BLOCK: usize = 100;
+
+    /// A 3x3 room with a door in the east wall, on the middle row.
+    fn room() -> Maze {
+        let rows = ["+---+", "|   |", "|   D", "|   |", "+---+"];
+        Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect())
     }
-}
\ No newline at end of file
+
+    /// Casts from (`x`, `y`), in blocks, along `a`.
+    fn cast(maze: &Maze, x: f32, y: f32, a: f32) -> Intersect {
+        let player = Player {
+            pos: Vector2::new(x * BLOCK as f32, y * BLOCK as f32),
+            a,
+            fov: PI / 3.0,
+            health: 1,
+            speed: 1.0,
+        };
+        let mut framebuffer = Framebuffer::new(1, 1, Color::BLACK);
+        cast_ray(&mut framebuffer, maze, &player, a, BLOCK, false)
+    }
+
+    #[track_caller]
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn hits_each_face_at_the_right_distance() {
+        let maze = room();
+
+        let east = cast(&maze, 1.5, 1.25, 0.0);
+        assert_close(east.distance, 250.0);
+        assert_eq!((east.side, east.impact, east.cell_x, east.cell_y), (Side::West, Cell::VerticalWall, 4, 1));
+
+        let south = cast(&maze, 1.25, 1.5, PI / 2.0);
+        assert_close(south.distance, 250.0);
+        assert_eq!((south.side, south.impact, south.cell_x, south.cell_y), (Side::North, Cell::HorizontalWall, 1, 4));
+
+        let west = cast(&maze, 2.5, 1.25, PI);
+        assert_close(west.distance, 150.0);
+        assert_eq!((west.side, west.cell_x, west.cell_y), (Side::East, 0, 1));
+
+        let north = cast(&maze, 2.25, 3.5, -PI / 2.0);
+        assert_close(north.distance, 250.0);
+        assert_eq!((north.side, north.cell_x, north.cell_y), (Side::South, 2, 0));
+    }
+
+    #[test]
+    fn diagonal_ray_distance_is_along_the_ray() {
+        // two blocks east for every block south, reaching x = 4 at y = 3.25
+        let hit = cast(&room(), 2.5, 2.5, 1.0_f32.atan2(2.0));
+        assert_close(hit.distance, 1.5 * 5.0_f32.sqrt() / 2.0 * BLOCK as f32);
+        assert_eq!((hit.side, hit.cell_x, hit.cell_y), (Side::West, 4, 3));
+        assert_close(hit.wall_x, 0.25);
+    }
+
+    #[test]
+    fn tx_follows_the_hit_position_left_to_right() {
+        let maze = room();
+        // facing east, left is north: a hit a quarter down the face is a quarter across
+        assert_eq!(cast(&maze, 1.5, 1.25, 0.0).tx, 32);
+        assert_eq!(cast(&maze, 1.5, 1.75, 0.0).tx, 96);
+        // facing south, left is east
+        assert_eq!(cast(&maze, 1.25, 1.5, PI / 2.0).tx, 96);
+        // facing west, left is south
+        assert_eq!(cast(&maze, 2.5, 1.25, PI).tx, 96);
+        // facing north, left is west
+        assert_eq!(cast(&maze, 2.25, 3.5, -PI / 2.0).tx, 32);
+        // the middle of a face
+        assert_eq!(cast(&maze, 1.5, 1.5, 0.0).tx, 64);
+    }
+
+    #[test]
+    fn rays_pass_the_gap_of_an_open_door() {
+        let mut maze = room();
+        let closed = cast(&maze, 1.5, 2.5, 0.0);
+        assert_eq!(closed.impact, Cell::Door);
+        assert_close(closed.distance, 250.0);
+        assert_eq!(closed.tx, 64);
+
+        // a quarter open: the panel slid south, to the viewer's right, and its texture with it
+        maze.set_door_openness(4, 2, 0.25);
+        let panel = cast(&maze, 1.5, 2.5, 0.0);
+        assert_eq!(panel.impact, Cell::Door);
+        assert_eq!(panel.tx, 32);
+
+        // three quarters open: the middle of the doorway is clear and the ray leaves the maze
+        maze.set_door_openness(4, 2, 0.75);
+        let through = cast(&maze, 1.5, 2.5, 0.0);
+        assert_eq!(through.impact, Cell::Empty);
+        assert_close(through.distance, 200.0 * BLOCK as f32);
+    }
+
+    #[test]
+    fn a_door_slides_the_same_way_seen_from_either_side() {
+        // two rooms with a two cell door between them, half open: the top cell is
+        // the gap, the bottom one holds the panel
+        let rows = ["+-----+", "|  |  |", "|  D  |", "|  D  |", "|  |  |", "+-----+"];
+        let mut maze = Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect());
+        maze.set_door_openness(3, 2, 0.5);
+        maze.set_door_openness(3, 3, 0.5);
+
+        // through the gap to the far wall, from the west and from the east
+        let from_west = cast(&maze, 1.5, 2.5, 0.0);
+        assert_eq!((from_west.impact, from_west.cell_x), (Cell::VerticalWall, 6));
+        assert_close(from_west.distance, 450.0);
+        let from_east = cast(&maze, 5.5, 2.5, PI);
+        assert_eq!((from_east.impact, from_east.cell_x), (Cell::VerticalWall, 0));
+        assert_close(from_east.distance, 450.0);
+
+        // the panel blocks the lower cell from both sides
+        let from_west = cast(&maze, 1.5, 3.25, 0.0);
+        let from_east = cast(&maze, 5.5, 3.25, PI);
+        assert_eq!((from_west.impact, from_west.cell_y), (Cell::Door, 3));
+        assert_eq!((from_east.impact, from_east.cell_y), (Cell::Door, 3));
+        assert_close(from_west.distance, 150.0);
+        assert_close(from_east.distance, 150.0);
+        // both see the same point of the panel, mirrored like any wall face
+        assert_eq!(from_west.tx, 32);
+        assert_eq!(from_east.tx, 96);
+    }
+
+    #[test]
+    fn a_ray_inside_a_doorway_meets_the_panel_edge() {
+        // the same half open door, cast from inside its gap: the door runs down
+        // its column even though the ray crosses a north-south face into it
+        let rows = ["+-----+", "|  |  |", "|  D  |", "|  D  |", "|  |  |", "+-----+"];
+        let mut maze = Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect());
+        maze.set_door_openness(3, 2, 0.5);
+        maze.set_door_openness(3, 3, 0.5);
+
+        let south = cast(&maze, 3.25, 2.5, PI / 2.0);
+        assert_eq!((south.impact, south.side, south.cell_y), (Cell::Door, Side::North, 3));
+        assert_close(south.distance, 50.0);
+        let north = cast(&maze, 3.25, 2.5, -PI / 2.0);
+        assert_eq!((north.impact, north.cell_y), (Cell::VerticalWall, 1));
+        assert_close(north.distance, 50.0);
+
+        // three quarters open, the edge is halfway down the lower cell
+        maze.set_door_openness(3, 2, 0.75);
+        maze.set_door_openness(3, 3, 0.75);
+        let south = cast(&maze, 3.25, 2.5, PI / 2.0);
+        assert_eq!((south.impact, south.cell_y), (Cell::Door, 3));
+        assert_close(south.distance, 100.0);
+        assert_eq!(south.tx, 96);
+    }
+
+    #[test]
+    fn drawing_the_ray_does_not_change_the_hit() {
+        let maze = room();
+        let player = Player { pos: Vector2::new(150.0, 125.0), a: 0.0, fov: PI / 3.0, health: 1, speed: 1.0 };
+        let mut framebuffer = Framebuffer::new(500, 500, Color::BLACK);
+        let drawn = cast_ray(&mut framebuffer, &maze, &player, 0.0, BLOCK, true);
+        assert_close(drawn.distance, 250.0);
+        assert_eq!(drawn.tx, 32);
+        assert_eq!(framebuffer.get_color(300, 125), Color::WHITE);
+        assert_eq!(framebuffer.get_color(300, 300), Color::BLACK);
+    }
+}
@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+
+use raylib::prelude::Color;
+
+use crate::ai::AiParams;
+use crate::animation::{Animation, AnimationSet, Frame};
+
+/// Stats and looks shared by every enemy of one type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnemyType {
+    /// Name used in level files, e.g. `enemy = pumpkin 12 4`
+    pub name: &'static str,
+    /// Health taken from the player per hit
+    pub damage: i32,
+    /// How close it has to get to hit, in blocks
+    pub hit_radius: f32,
+    pub health: i32,
+    pub animations: AnimationSet,
+    /// Multiplied into the sprite colors, WHITE leaves them as they are
+    pub tint: Color,
+    /// Played when it hits the player
+    pub sound: &'static str,
+    pub minimap_color: Color,
+    /// Sight and speed, see `ai`
+    pub ai: AiParams,
+}
+
+// carved face in front, plain back
+const FACE: Frame = Frame::new('e');
+const BACK: Frame = Frame::new('b');
+
+/// Pumpkins rock in place, roll when they move and lunge forward to bite.
+const PUMPKIN_ANIMATIONS: AnimationSet = AnimationSet {
+    idle: Animation {
+        frames: &[&[FACE, BACK], &[FACE.flipped(), BACK.flipped()]],
+        fps: 2.0,
+        looping: true,
+    },
+    walk: Animation {
+        frames: &[&[FACE, BACK], &[FACE.turned(1), BACK.turned(1)], &[FACE.turned(2), BACK.turned(2)], &[FACE.turned(3), BACK.turned(3)]],
+        fps: 4.0,
+        looping: true,
+    },
+    attack: Animation {
+        frames: &[&[FACE.turned(3)], &[FACE], &[FACE.turned(1)], &[FACE]],
+        fps: 10.0,
+        looping: false,
+    },
+    hurt: Animation {
+        frames: &[&[BACK], &[BACK.turned(2)], &[BACK]],
+        fps: 8.0,
+        looping: false,
+    },
+};
+
+/// Wisps flicker between both images all the time.
+const WISP_ANIMATIONS: AnimationSet = AnimationSet {
+    idle: Animation {
+        frames: &[&[FACE], &[BACK]],
+        fps: 6.0,
+        looping: true,
+    },
+    walk: Animation {
+        frames: &[&[FACE], &[BACK], &[FACE.flipped()], &[BACK.flipped()]],
+        fps: 10.0,
+        looping: true,
+    },
+    attack: Animation { frames: &[], fps: 1.0, looping: false },
+    hurt: Animation { frames: &[], fps: 1.0, looping: false },
+};
+
+/// The enemy registry. The first entry is the default type.
+pub const ENEMY_TYPES: &[EnemyType] = &[
+    EnemyType {
+        name: "pumpkin",
+        damage: 1,
+        hit_radius: 1.0 / 3.5,
+        health: 3,
+        animations: PUMPKIN_ANIMATIONS,
+        tint: Color::WHITE,
+        sound: "assets/hit1.ogg",
+        minimap_color: Color::ORANGE,
+        ai: AiParams {
+            view_distance: 6.0,
+            fov: PI / 2.0,
+            speed: 1.2,
+            chase_speed: 2.0,
+            search_time: 4.0,
+            patrol_range: 8,
+        },
+    },
+    // fast and fragile, sees far but gives up quickly
+    EnemyType {
+        name: "wisp",
+        damage: 1,
+        hit_radius: 0.2,
+        health: 1,
+        animations: WISP_ANIMATIONS,
+        tint: Color::new(150, 200, 255, 255),
+        sound: "assets/hit1.ogg",
+        minimap_color: Color::VIOLET,
+        ai: AiParams {
+            view_distance: 8.0,
+            fov: PI * 2.0 / 3.0,
+            speed: 2.0,
+            chase_speed: 3.2,
+            search_time: 2.0,
+            patrol_range: 12,
+        },
+    },
+    // slow, hits hard and keeps searching for a long time
+    EnemyType {
+        name: "brute",
+        damage: 2,
+        hit_radius: 0.4,
+        health: 6,
+        animations: PUMPKIN_ANIMATIONS,
+        tint: Color::new(255, 110, 110, 255),
+        sound: "assets/hit1.ogg",
+        minimap_color: Color::MAROON,
+        ai: AiParams {
+            view_distance: 5.0,
+            fov: PI / 3.0,
+            speed: 0.7,
+            chase_speed: 1.3,
+            search_time: 6.0,
+            patrol_range: 5,
+        },
+    },
+];
+
+/// Looks up a type by its level file name.
+pub fn enemy_type(name: &str) -> Option<&'static EnemyType> {
+    ENEMY_TYPES.iter().find(|t| t.name == name)
+}
+
+pub fn default_type() -> &'static EnemyType {
+    &ENEMY_TYPES[0]
+}
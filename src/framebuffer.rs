@@ -1,78 +1,380 @@
 use raylib::prelude::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 
 use crate::game::AppState;
+use crate::resolution::Filter;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    Off,
+    /// Ramps from no fog at `start` to full fog at `end`
+    Linear,
+    /// 1 - e^(-density * (depth - start))
+    Exponential,
+}
+
+/// Distance fog blended into every pixel drawn with a depth.
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Color,
+    pub start: f32,
+    pub end: f32,
+    pub density: f32,
+}
+
+impl Fog {
+    pub const OFF: Fog = Fog { mode: FogMode::Off, color: Color::BLACK, start: 0.0, end: 0.0, density: 0.0 };
+
+    pub const fn linear(color: Color, start: f32, end: f32) -> Self {
+        Fog { mode: FogMode::Linear, color, start, end, density: 0.0 }
+    }
+
+    pub const fn exponential(color: Color, start: f32, density: f32) -> Self {
+        Fog { mode: FogMode::Exponential, color, start, end: f32::INFINITY, density }
+    }
+
+    /// How much of the fog color to use at this depth, 0.0 (none) to 1.0 (all fog).
+    pub fn factor(&self, depth: f32) -> f32 {
+        let d = (depth - self.start).max(0.0);
+        match self.mode {
+            FogMode::Off => 0.0,
+            FogMode::Linear => {
+                if self.end <= self.start {
+                    return if depth >= self.end { 1.0 } else { 0.0 };
+                }
+                (d / (self.end - self.start)).clamp(0.0, 1.0)
+            }
+            FogMode::Exponential => 1.0 - (-self.density * d).exp(),
+        }
+    }
+
+    pub fn apply(&self, color: Color, depth: f32) -> Color {
+        self.mix(color, self.factor(depth))
+    }
+
+    /// Blends `f` of the fog color into `color`, `f` from `factor`.
+    pub fn mix(&self, color: Color, f: f32) -> Color {
+        if f <= 0.0 {
+            return color;
+        }
+        let mix = |c: u8, fog: u8| (c as f32 + (fog as f32 - c as f32) * f) as u8;
+        Color::new(mix(color.r, self.color.r), mix(color.g, self.color.g), mix(color.b, self.color.b), color.a)
+    }
+}
+
+/// Sky and floor color behind the walls.
+pub const BACKGROUND_COLOR: Color = Color::new(50, 50, 100, 255);
+
+/// Packs a color so that in memory it has raylib's R8G8B8A8 byte order.
+pub fn pack(color: Color) -> u32 {
+    u32::from_ne_bytes([color.r, color.g, color.b, color.a])
+}
+
+pub fn unpack(pixel: u32) -> Color {
+    let [r, g, b, a] = pixel.to_ne_bytes();
+    Color::new(r, g, b, a)
+}
+
+fn pixel_bytes(pixels: &[u32]) -> &[u8] {
+    // each u32 is four bytes in R, G, B, A order, see `pack`
+    unsafe { std::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) }
+}
+
+/// The software render target: a plain pixel buffer plus a depth buffer. It only
+/// becomes a raylib image when it is presented or saved.
 pub struct Framebuffer {
     pub width: u32,
     pub height: u32,
-    pub color_buffer: Image,
+    /// Row-major, one `pack`ed RGBA color per pixel
+    pub pixels: Vec<u32>,
     background_color: Color,
     current_color: Color,
     zbuffer: Vec<f32>,
+    pub fog: Fog,
+    /// How the frame is scaled to the window
+    pub filter: Filter,
+    /// GPU copy of `pixels`, created on the first present and updated in place after that
+    texture: Option<Texture2D>,
+    /// Filter last set on `texture`
+    texture_filter: Option<Filter>,
 }
 
 impl Framebuffer {
     pub fn new(width: u32, height: u32, background_color: Color) -> Self {
-        let color_buffer = Image::gen_image_color(width as i32, height as i32, background_color);
-        let zbuffer = vec![f32::INFINITY; (width*height) as usize];
+        let size = (width * height) as usize;
         Framebuffer {
             width,
             height,
-            color_buffer,
+            pixels: vec![pack(background_color); size],
             background_color,
             current_color: Color::WHITE,
-            zbuffer,
+            zbuffer: vec![f32::INFINITY; size],
+            fog: Fog::OFF,
+            filter: Filter::Nearest,
+            texture: None,
+            texture_filter: None,
         }
     }
 
-    /// Clears the framebuffer by regenerating the color buffer with the background color
+    /// Changes the size, clearing the buffer. The texture is made again on the
+    /// next present.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        let size = (width * height) as usize;
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![pack(self.background_color); size];
+        self.zbuffer = vec![f32::INFINITY; size];
+        self.texture = None;
+    }
+
+    /// Fills the buffer with the background color and resets the depth buffer.
     pub fn clear(&mut self) {
-        self.color_buffer = Image::gen_image_color(self.width as i32, self.height as i32, self.background_color);
-        self.zbuffer.fill(f32::INFINITY);
+        self.fill_rows(0, self.height, self.background_color);
     }
 
-    /// Sets a single pixel in the buffer to the current color, if within bounds
+    /// Sets a single pixel in the buffer to the current color, if within bounds.
+    /// The color is blended towards the fog color according to `depth`.
     pub fn set_pixel(&mut self, x: u32, y: u32, depth: f32) {
-        if x < self.width && y < self.height && depth < self.zbuffer[(y*self.width + x) as usize] {
-            // Calculate the offset into the Image data (raylib stores pixels in row-major order)
-            self.color_buffer.draw_pixel(x as i32, y as i32, self.current_color);
-            self.zbuffer[(y*self.width + x) as usize] = depth;
-            //let offset = (y * self.width + x) as usize;
-            // Safety: data is a contiguous Vec<u8> RGBA, but raylib-rs Image uses Color per u32
-            //unsafe {
-                //let ptr = self.color_buffer.data().as_mut_ptr() as *mut Color;
-                //*ptr.add(offset) = self.current_color;
-            //}
+        if x < self.width && y < self.height {
+            let index = (y * self.width + x) as usize;
+            if depth < self.zbuffer[index] {
+                self.pixels[index] = pack(self.fog.apply(self.current_color, depth));
+                self.zbuffer[index] = depth;
+            }
         }
     }
-    pub fn get_color(&mut self, x: u32, y: u32) {
-        self.color_buffer.get_color(x as i32, y as i32);
+
+    pub fn get_color(&self, x: u32, y: u32) -> Color {
+        if x < self.width && y < self.height {
+            unpack(self.pixels[(y * self.width + x) as usize])
+        } else {
+            Color::BLANK
+        }
+    }
+
+    /// Fills rows `y0..y1` with `color`, ignoring depth and fog.
+    pub fn fill_rows(&mut self, y0: u32, y1: u32, color: Color) {
+        let y1 = y1.min(self.height);
+        if y0 >= y1 {
+            return;
+        }
+        let range = (y0 * self.width) as usize..(y1 * self.width) as usize;
+        self.pixels[range.clone()].fill(pack(color));
+        self.zbuffer[range].fill(f32::INFINITY);
+    }
+
+    /// Vertical run of pixels `y0..y1` in column `x`, all at the same depth, so
+    /// fog is worked out once for the whole span. `color_at` gives the color of
+    /// each row.
+    pub fn draw_span(&mut self, x: u32, y0: u32, y1: u32, depth: f32, mut color_at: impl FnMut(u32) -> Color) {
+        if x >= self.width {
+            return;
+        }
+        let fog = self.fog.factor(depth);
+        for y in y0..y1.min(self.height) {
+            let index = (y * self.width + x) as usize;
+            if depth < self.zbuffer[index] {
+                self.pixels[index] = pack(self.fog.mix(color_at(y), fog));
+                self.zbuffer[index] = depth;
+            }
+        }
+    }
+
+    /// `draw_span` in a single color.
+    pub fn fill_span(&mut self, x: u32, y0: u32, y1: u32, depth: f32, color: Color) {
+        if x >= self.width {
+            return;
+        }
+        let pixel = pack(self.fog.apply(color, depth));
+        for y in y0..y1.min(self.height) {
+            let index = (y * self.width + x) as usize;
+            if depth < self.zbuffer[index] {
+                self.pixels[index] = pixel;
+                self.zbuffer[index] = depth;
+            }
+        }
+    }
+
+    /// Copies a `w` x `h` block of packed pixels to (`x`, `y`), clipped to the
+    /// buffer. Depth is reset under the copied pixels. Rows missing from the end
+    /// of `src` are left alone.
+    pub fn blit(&mut self, x: u32, y: u32, w: u32, h: u32, src: &[u32]) {
+        if x >= self.width || y >= self.height || w == 0 {
+            return;
+        }
+        let cols = w.min(self.width - x) as usize;
+        let rows = h.min(self.height - y).min((src.len() / w as usize) as u32);
+        for row in 0..rows {
+            let from = (row * w) as usize;
+            let to = ((y + row) * self.width + x) as usize;
+            self.pixels[to..to + cols].copy_from_slice(&src[from..from + cols]);
+            self.zbuffer[to..to + cols].fill(f32::INFINITY);
+        }
+    }
+
+    /// The pixels as RGBA bytes, ready for an image or texture upload.
+    pub fn as_bytes(&self) -> &[u8] {
+        pixel_bytes(&self.pixels)
+    }
+
+    /// Copies the buffer into a new raylib image.
+    pub fn to_image(&self) -> Image {
+        let image = Image::gen_image_color(self.width as i32, self.height as i32, Color::BLANK);
+        let bytes = self.as_bytes();
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), image.data as *mut u8, bytes.len());
+        }
+        image
     }
 
     pub fn set_background_color(&mut self, color: Color) {
         self.background_color = color;
     }
 
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+
     pub fn set_current_color(&mut self, color: Color) {
         self.current_color = color;
     }
 
     /// Exports the framebuffer to an image file (BMP/PNG/etc.) using raylib's FFI
     pub fn render_to_file(&self, file_path: &str) {
-        self.color_buffer.export_image(file_path);
+        self.to_image().export_image(file_path);
+    }
+
+    /// Writes the frame as a binary PPM (P6). Alpha is dropped.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let rgb: Vec<u8> = self.pixels.iter().flat_map(|&p| {
+            let c = unpack(p);
+            [c.r, c.g, c.b]
+        }).collect();
+        out.write_all(&rgb)
+    }
+
+    /// Saves the frame to `path`: PPM for a `.ppm` extension, PNG otherwise.
+    /// Needs no window, unlike anything that touches the GPU texture.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let is_ppm = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ppm"));
+        if is_ppm {
+            let mut bytes = Vec::new();
+            self.write_ppm(&mut bytes).map_err(|err| err.to_string())?;
+            return fs::write(path, bytes).map_err(|err| format!("{}: {}", path.display(), err));
+        }
+
+        let name = path.to_str().ok_or_else(|| format!("{}: not a valid UTF-8 path", path.display()))?;
+        // raylib only logs export errors, so look for the file afterwards
+        let _ = fs::remove_file(path);
+        self.to_image().export_image(name);
+        if path.is_file() { Ok(()) } else { Err(format!("{}: could not write the image", path.display())) }
     }
 
+    /// Uploads the pixels to the GPU texture, creating it first if needed.
+    fn upload(&mut self, window: &mut RaylibHandle, raylib_thread: &RaylibThread) -> Result<(), String> {
+        let stale = self.texture.as_ref().is_some_and(|t| t.width as u32 != self.width || t.height as u32 != self.height);
+        if stale {
+            self.texture = None;
+            self.texture_filter = None;
+        }
+        match &mut self.texture {
+            Some(texture) => {
+                texture.update_texture(pixel_bytes(&self.pixels)).map_err(|err| err.to_string())
+            }
+            None => {
+                let texture = window
+                    .load_texture_from_image(raylib_thread, &self.to_image())
+                    .map_err(|err| err.to_string())?;
+                self.texture = Some(texture);
+                Ok(())
+            }
+        }
+    }
+
+    /// Presents the frame stretched over the whole window, with the HUD on top.
+    /// If the upload fails the HUD is still drawn and the error is returned.
     pub fn swap_buffers(
-        &self,
+        &mut self,
         window: &mut RaylibHandle,
         raylib_thread: &RaylibThread,
         state: &AppState,
-    ) {
-        if let Ok(texture) = window.load_texture_from_image(raylib_thread, &self.color_buffer) {
-            let mut renderer = window.begin_drawing(raylib_thread);
-            renderer.draw_texture(&texture, 0, 0, Color::WHITE);
-            if state.is_playing {
-                state.render_game_screen_extras(&mut renderer);
+    ) -> Result<(), String> {
+        let uploaded = self.upload(window, raylib_thread);
+        if let Some(texture) = &self.texture
+            && self.texture_filter != Some(self.filter)
+        {
+            let mode = match self.filter {
+                Filter::Nearest => TextureFilter::TEXTURE_FILTER_POINT,
+                Filter::Linear => TextureFilter::TEXTURE_FILTER_BILINEAR,
+            };
+            texture.set_texture_filter(raylib_thread, mode);
+            self.texture_filter = Some(self.filter);
+        }
+
+        let (window_w, window_h) = (window.get_screen_width() as f32, window.get_screen_height() as f32);
+        let mut renderer = window.begin_drawing(raylib_thread);
+        match (&uploaded, &self.texture) {
+            (Ok(()), Some(texture)) => {
+                let source = Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32);
+                let dest = Rectangle::new(0.0, 0.0, window_w, window_h);
+                renderer.draw_texture_pro(texture, source, dest, Vector2::zero(), 0.0, Color::WHITE);
             }
+            _ => renderer.clear_background(self.background_color),
         }
+        if state.is_playing {
+            state.render_game_screen_extras(&mut renderer);
+        }
+        uploaded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_rows_covers_the_rows_and_resets_depth() {
+        let mut framebuffer = Framebuffer::new(3, 4, Color::BLACK);
+        framebuffer.set_current_color(Color::RED);
+        framebuffer.set_pixel(1, 2, 10.0);
+
+        // past the bottom is clipped
+        framebuffer.fill_rows(2, 9, Color::BLUE);
+        assert_eq!(framebuffer.get_color(1, 1), Color::BLACK);
+        assert_eq!(framebuffer.get_color(0, 2), Color::BLUE);
+        assert_eq!(framebuffer.get_color(2, 3), Color::BLUE);
+        // the red pixel's depth went with it, so anything may draw there again
+        framebuffer.set_pixel(1, 2, 100.0);
+        assert_eq!(framebuffer.get_color(1, 2), Color::RED);
+
+        framebuffer.fill_rows(3, 1, Color::GREEN);
+        assert_eq!(framebuffer.get_color(0, 2), Color::BLUE);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn blit_clips_to_the_buffer_and_a_short_source() {
+        let mut framebuffer = Framebuffer::new(4, 3, Color::BLACK);
+        let (red, green) = (pack(Color::RED), pack(Color::GREEN));
+        // 3 x 2 block hanging off the right and bottom edges
+        framebuffer.blit(2, 2, 3, 2, &[red, green, red, green, red, green]);
+        assert_eq!(framebuffer.get_color(2, 2), Color::RED);
+        assert_eq!(framebuffer.get_color(3, 2), Color::GREEN);
+        assert_eq!(framebuffer.get_color(1, 2), Color::BLACK);
+
+        // a source holding one row and a bit for a 2 x 3 block: only the whole row lands
+        framebuffer.blit(0, 0, 2, 3, &[green, green, red]);
+        assert_eq!(framebuffer.get_color(1, 0), Color::GREEN);
+        assert_eq!(framebuffer.get_color(0, 1), Color::BLACK);
+
+        framebuffer.blit(4, 0, 1, 1, &[red]);
+        framebuffer.blit(0, 0, 0, 1, &[]);
+        assert_eq!(framebuffer.get_color(0, 0), Color::GREEN);
+    }
+}
@@ -1,8 +1,6 @@
-use std::f32::consts::PI;
-
 use raylib::prelude::*;
 
-use crate::{gui::{Screen, element::{Element, Elements}, screens::Screens}, maze::{Maze, load_maze}, player::Player, sprite::Enemy, textures::TextureManager};
+use crate::{framebuffer::Fog, gui::{Screen, element::{Element, Elements}, screens::{LevelRow, Screens}}, level::{self, Level, load_level}, maze::{KeyColor, MazeError, generator::{self, Algorithm, GeneratorConfig}}, packs::{self, LevelPack}, resolution::RenderSettings, rng, player::read_input, sim::{self, Outcome, Stepper, World}, textures::TextureManager};
 
 pub struct AppState {
     pub current_screen: Screens,
@@ -10,81 +8,161 @@ pub struct AppState {
     pub height: i32,
     pub current_level: usize,
     pub is_playing: bool,
-    pub player: Player,
-    pub enemies: Vec<Enemy>,
-    pub mazes: Vec<Maze>,
+    /// Level in play; None until the first level starts
+    pub world: Option<World>,
+    /// Turns frame time into fixed world steps
+    pub stepper: Stepper,
+    /// Level packs in play order; their files make up the start of `levels`
+    pub packs: Vec<LevelPack>,
+    /// Loaded levels, pack after pack; a file that failed to load keeps its error so the menu can show it
+    pub levels: Vec<Result<Level, MazeError>>,
+    /// How many of `levels` come from files; a generated level is kept right after them
+    pub file_levels: usize,
+    /// First visible row of the main menu level list
+    pub level_scroll: usize,
     pub paused: bool,
     pub enabled_cursor: bool, // true = cursor free/visible, false = captured
     pub block_size: f32,
     pub max_health: i32,
     pub close_window: bool,
-    pub hit_frame: bool,
+    /// Hit sound to play this frame
+    pub hit_sound: Option<&'static str>,
+    /// Internal resolution of the 3D view, see `resolution`
+    pub render: RenderSettings,
     pub texture_manager: TextureManager,
-    animation_left: f64,
+    /// Seconds left of the red flash after a hit
     remaining_cooldown: f64,
-    cooldown: f64,
-    last_hit: f64,
+    /// Seconds left of the "locked" message
+    locked_message_left: f64,
+    /// Color of the key the locked door needs
+    locked_color: KeyColor,
 }
 
 impl AppState {
     pub fn init(w: i32, h: i32, block_size: f32, tm: TextureManager) -> Self {
-        let max_health = 5;
-        let current_screen = Screens::main_menu(w, h);
-        let maze1 = load_maze("maze1.txt");
-        let maze2 = load_maze("maze2.txt");
-        let maze3 = load_maze("maze3.txt");
-        let player = Player { 
-            pos: Vector2::new(block_size, block_size,),
-            a: PI/3.0, 
-            fov: PI/3.0, 
-            health: max_health
-        };
-        AppState { 
-            current_screen,
+        let max_health = sim::MAX_HEALTH;
+        let packs = packs::discover_packs();
+        let levels: Vec<_> = packs
+            .iter()
+            .flat_map(|pack| &pack.levels)
+            .map(|path| load_level(&path.to_string_lossy()))
+            .collect();
+        let mut state = AppState { 
+            current_screen: Screens::main_menu(w, h, &[], 0, 0),
              width: w, 
              height: h, 
              current_level: 0, 
              is_playing: false, 
-             player: player, 
-             enemies: vec![], 
-             mazes: vec![maze1, maze2, maze3], 
+             world: None,
+             stepper: Stepper::new(),
+             file_levels: levels.len(),
+             levels,
+             packs,
+             level_scroll: 0,
              paused: false, 
              enabled_cursor: true, 
              block_size: block_size, 
              max_health, 
              close_window: false,
-             hit_frame: false,
+             hit_sound: None,
+             render: RenderSettings::new(),
              remaining_cooldown: 0.0,
-             cooldown: 1.0,
-             last_hit: -1.0,
+             locked_message_left: 0.0,
+             locked_color: KeyColor::Gold,
              texture_manager: tm,
-             animation_left: 0.0,
+        };
+        state.current_screen = state.main_menu();
+        state
+
+    }
+
+    /// Level list for the main menu: each pack's name followed by its levels, by
+    /// the name in their header. Levels that failed to load show their file name so it can be fixed.
+    fn level_rows(&self) -> Vec<LevelRow> {
+        let mut rows = Vec::new();
+        let mut index = 0;
+        for pack in &self.packs {
+            rows.push(LevelRow::Pack(pack.name.clone()));
+            for _ in &pack.levels {
+                let name = match &self.levels[index] {
+                    Ok(level) => level.name.clone(),
+                    Err(err) => format!("{} (error)", err.path()),
+                };
+                rows.push(LevelRow::Level(index, name));
+                index += 1;
+            }
         }
+        rows
+    }
+
+    pub fn main_menu(&self) -> Screens {
+        Screens::main_menu(self.width, self.height, &self.level_rows(), self.level_scroll, self.current_level)
+    }
 
+    /// Lays the screens out again for a window of `w` x `h`. The current
+    /// screen is rebuilt; the main menu keeps the seed, algorithm and error shown.
+    pub fn resize(&mut self, w: i32, h: i32) {
+        if (w, h) == (self.width, self.height) {
+            return;
+        }
+        self.width = w;
+        self.height = h;
+        self.current_screen = match &self.current_screen {
+            Screens::MainMenu(old) => {
+                let mut menu = self.main_menu();
+                keep_menu_input(old, &mut menu, h);
+                menu
+            }
+            Screens::Game(_) => Screens::game(w, h),
+            Screens::Pause(_) => Screens::pause(w, h),
+            Screens::Victory(_) => Screens::victory(w, h),
+            Screens::Defeat(_) => Screens::defeat(w, h),
+        };
     }
 
+    /// Moves the main menu level list by `rows`, keeping it within the list.
+    fn scroll_levels(&mut self, rows: isize) {
+        let level_rows = self.level_rows();
+        let max_scroll = level_rows.len().saturating_sub(Screens::VISIBLE_LEVEL_ROWS);
+        self.level_scroll = self.level_scroll.saturating_add_signed(rows).min(max_scroll);
+        let panel = Screens::level_panel(self.width, &level_rows, self.level_scroll, self.current_level);
+        if let Screens::MainMenu(screen) = &mut self.current_screen {
+            screen.elements.insert("levels_panel".to_string(), Elements::Panel(panel));
+        }
+    }
 
     /// Called when we click "Play" in the main menu.
+    /// A level that failed to load sends us back to the menu with its error instead.
     pub fn start_level(&mut self, window: &mut RaylibHandle) {
+        let problem = match self.levels.get(self.current_level) {
+            Some(Ok(_)) => None,
+            Some(Err(err)) => Some(err.to_string()),
+            None => Some(format!("no levels found in {}/", packs::LEVELS_DIR)),
+        };
+        if let Some(message) = problem {
+            self.is_playing = false;
+            self.enabled_cursor = true;
+            window.enable_cursor();
+            window.show_cursor();
+            let mut menu = self.main_menu();
+            if let Screens::MainMenu(old) = &self.current_screen {
+                keep_menu_input(old, &mut menu, self.height);
+            }
+            self.current_screen = menu;
+            self.current_screen.show_error(self.height, &message);
+            return;
+        }
+
         self.is_playing = true;
         self.paused = false;
         self.enabled_cursor = false;
-        self.last_hit = -self.cooldown;
         self.remaining_cooldown = 0.0;
-        self.animation_left = 0.0;
-
-        // Reset player
-        self.player.health = self.max_health;
-        // e.g. find cell 's' in the maze and set player.pos
-        let maze = &self.mazes[self.current_level];
-        if let Some((i, j)) = find_start_cell(maze, 's') {
-            self.player.pos.x = (i as f32 + 0.5) * self.block_size;
-            self.player.pos.y = (j as f32 + 0.5) * self.block_size;
-        }
-        self.player.a = PI/4.0;
+        self.locked_message_left = 0.0;
 
-        // Reset enemies for this level
-        self.spawn_enemies_for_level();
+        // Fresh world for this level: player on the start cell, enemies on their spawns
+        let seed = unsafe { raylib::ffi::GetRandomValue(0, i32::MAX) } as u64;
+        self.world = Some(World::new(self.current_level_data(), self.block_size, self.max_health, seed));
+        self.stepper = Stepper::new();
 
         // Capture mouse
         window.disable_cursor();
@@ -94,128 +172,37 @@ impl AppState {
         self.current_screen = Screens::game(self.width, self.height);
     }
 
-    fn current_maze(&self) -> &Maze {
-        &self.mazes[self.current_level]
-    }
-
-    fn current_maze_mut(&mut self) -> &mut Maze {
-        &mut self.mazes[self.current_level]
-    }
-
-    fn spawn_enemies_for_level(&mut self) {
-        let mut pos1 = Vector2::new(1.0, 1.0);
-        let mut pos2 = Vector2::new(1.0, 1.0);
-        match self.current_level {
-            0 => {
-                pos1.x = 11.0 * self.block_size; pos1.y = 3.0 * self.block_size;
-                pos2.x = 7.0 * self.block_size; pos2.y = 7.0 * self.block_size;
-            }
-            1 => {
-                pos1.x = 11.0 * self.block_size; pos1.y = 3.0 * self.block_size;
-                pos2.x = 11.0 * self.block_size; pos2.y = 7.0 * self.block_size;
-            }
-            2 => {
-                pos1.x = 1.0 * self.block_size; pos1.y = 11.0 * self.block_size;
-                pos2.x = 1.0 * self.block_size; pos2.y = 5.0 * self.block_size;
-            }
-            _ => {}
-        }
-        self.enemies = vec![
-            Enemy {pos: pos1, texture_key: 'e'},
-            Enemy {pos: pos2, texture_key: 'e'}
-        ];
+    /// Level being played. Only valid levels can be started, see `start_level`.
+    pub fn current_level_data(&self) -> &Level {
+        self.levels[self.current_level].as_ref().expect("current level failed to load")
     }
 
-    /// Move enemies one step each frame to some free neighboring cell.
-    fn update_enemies(&mut self, dt: f64) {
-        let mut maze = self.current_maze().clone();
-        use raylib::ffi::GetRandomValue;
-        let animation_rate = 1.0;
-        let move_rate = 0.25;
-
-        for enemy in &mut self.enemies {
-            // 4-neighborhood: up, down, left, right
-            let dirs = [
-                Vector2::new( 1.0,  0.0),
-                Vector2::new(-1.0,  0.0),
-                Vector2::new( 0.0,  1.0),
-                Vector2::new( 0.0, -1.0),
-            ];
-
-            let step = self.block_size / 10.0; // enemy step size
-
-            // Try up to 4 random directions
-            if dt%move_rate < 0.1 {
-                for _ in 0..4 {
-                    let idx = unsafe { GetRandomValue(0, 3) } as usize;
-                    let dir = dirs[idx];
-
-                    let next_x = enemy.pos.x + dir.x * step;
-                    let next_y = enemy.pos.y + dir.y * step;
-
-                    if AppState::is_free_cell(&mut maze, next_x, next_y, self.block_size) {
-                        enemy.pos.x = next_x;
-                        enemy.pos.y = next_y;
-                        break;
-                    }
-                }
-            }
-            if dt%animation_rate < 0.5*animation_rate {
-                enemy.texture_key = '#';
-            } else {
-                enemy.texture_key = 'e';
-            }
-        }
-        if self.animation_left <= 0.0 {
-            self.texture_manager.rotate_images();
-            self.animation_left = animation_rate;
-        }
+    pub fn current_fog(&self) -> Fog {
+        self.current_level_data().fog
     }
 
-    /// Check if a world position corresponds to a free maze cell (' ' or maybe 'g').
-    fn is_free_cell(maze: &Maze, x: f32, y: f32, block_size: f32) -> bool {
-        if x < 0.0 || y < 0.0 {
-            return false;
-        }
-        let i = (x / block_size) as usize;
-        let j = (y / block_size) as usize;
-        if j >= maze.len() || i >= maze[0].len() {
-            return false;
-        }
-        let c = maze[j][i];
-        c == ' ' || c == 'g'
+    /// World of the level in play. Only valid while a level has been started.
+    pub fn world(&self) -> &World {
+        self.world.as_ref().expect("no level has been started")
     }
 
-    /// Player wins if standing on a 'g' cell.
-    fn is_on_goal(&self) -> bool {
-        let maze = self.current_maze();
-        let i = (self.player.pos.x / self.block_size) as usize;
-        let j = (self.player.pos.y / self.block_size) as usize;
-        if j >= maze.len() || i >= maze[0].len() {
-            return false;
+    /// Track that should be playing: the level's own while in a level, the default one in menus.
+    pub fn current_music(&self) -> &str {
+        if self.is_playing {
+            self.current_level_data().music()
+        } else {
+            level::DEFAULT_MUSIC
         }
-        maze[j][i] == 'g'
     }
 
-    /// Player loses health when near an enemy.
-    fn check_enemy_collisions(&mut self, dt: f64) {
-        let damage_distance = self.block_size / 3.5; // tune
-        let damage = 1; // HP per frame per collision; you can adjust
-
-        for enemy in &self.enemies {
-            let dx = enemy.pos.x - self.player.pos.x;
-            let dy = enemy.pos.y - self.player.pos.y;
-            let dist_sq = dx * dx + dy * dy;
-            if dist_sq <= damage_distance * damage_distance && dt - self.last_hit >= self.cooldown {
-                self.last_hit = dt;
-                self.player.health -= damage;
-                self.hit_frame = true;
-                self.remaining_cooldown = self.cooldown;
-                if self.player.health < 0 {
-                    self.player.health = 0;
-                }
-            }
-        }
+    /// Generates a maze from the menu settings and makes it the current level.
+    /// It replaces the previous generated level, if there was one. Settings the
+    /// generator rejects give a level that fails to start, like a broken level file.
+    pub fn load_random_level(&mut self, config: &GeneratorConfig) {
+        let level = generator::generate(config).map(|maze| Level::from_maze(config.name(), maze));
+        self.levels.truncate(self.file_levels);
+        self.levels.push(level);
+        self.current_level = self.file_levels;
     }
 
     pub fn render_game_screen_extras(&self, d:&mut RaylibDrawHandle) {
@@ -224,6 +211,7 @@ impl AppState {
 
         // ---------- HUD OVERLAYS ----------
         let margin = 10;
+        let world = self.world();
         let screen_w = self.width;
         let screen_h = self.height;
 
@@ -237,6 +225,11 @@ impl AppState {
         let fps_y = screen_h - margin - 20;
         d.draw_text(&fps_text, fps_x, fps_y, fps_font_size, Color::YELLOW);
 
+        // ---- Render resolution, just above the FPS ----
+        let render_text = self.render.label();
+        let render_x = screen_w - d.measure_text(&render_text, 10) - margin;
+        d.draw_text(&render_text, render_x, fps_y - 14, 10, Color::LIGHTGRAY);
+
         // ---- Health as hearts (bottom-left corner) ----
         let heart_font_size = 30;
         let heart_spacing = heart_font_size; // horizontal spacing between hearts
@@ -245,7 +238,7 @@ impl AppState {
 
         for i in 0..self.max_health {
             let x = hearts_x + i * heart_spacing;
-            let color = if (i as i32) < self.player.health {
+            let color = if (i as i32) < world.player.health {
                 Color::GREEN
             } else {
                 Color::DARKGRAY
@@ -253,8 +246,37 @@ impl AppState {
             d.draw_text("*", x, hearts_y, heart_font_size, color);
         }
 
+        // ---- Keys, score and speed boost (above the hearts) ----
+        let items_font_size = 20;
+        let mut items_text = format!("Score: {}", world.score);
+        if !world.keys.is_empty() {
+            let names: Vec<&str> = world.keys.iter().map(|key| key.name()).collect();
+            items_text += &format!("   Keys: {}", names.join(" "));
+        }
+        if world.boost_left > 0.0 {
+            items_text += &format!("   Speed: {:.0}s", world.boost_left.ceil());
+        }
+        d.draw_text(&items_text, margin, hearts_y - items_font_size - 5, items_font_size, Color::GOLD);
+
+        // ---- Time limit (top-center) ----
+        if let Some(time_left) = world.time_left {
+            let seconds = time_left.max(0.0).ceil() as i32;
+            let time_text = format!("{}:{:02}", seconds / 60, seconds % 60);
+            let time_font_size = 30;
+            let time_x = (screen_w - d.measure_text(&time_text, time_font_size)) / 2;
+            let color = if seconds <= 10 { Color::RED } else { Color::WHITE };
+            d.draw_text(&time_text, time_x, margin, time_font_size, color);
+        }
+
+        if self.locked_message_left > 0.0 {
+            let text = format!("Locked - find a {} key", self.locked_color.name());
+            let font_size = 30;
+            let x = (screen_w - d.measure_text(&text, font_size)) / 2;
+            d.draw_text(&text, x, screen_h / 2 + 60, font_size, self.locked_color.color());
+        }
+
         if self.remaining_cooldown > 0.0 {
-            let t = self.remaining_cooldown / self.cooldown;
+            let t = self.remaining_cooldown / world.hit_cooldown;
             let max_a = 180.0;
             let alpha = (t*max_a) as u8;
             d.draw_rectangle(0, 0, self.width, self.height, Color::new(255, 0, 0, alpha));
@@ -263,16 +285,30 @@ impl AppState {
     }
 }
 
-/// Helper to locate a cell with a specific character in the maze.
-pub fn find_start_cell(maze: &Maze, ch: char) -> Option<(usize, usize)> {
-    for (j, row) in maze.iter().enumerate() {
-        for (i, &c) in row.iter().enumerate() {
-            if c == ch {
-                return Some((i, j));
-            }
+/// Copies what the player typed or picked in the old main menu into a freshly built one.
+fn keep_menu_input(old: &Screen, menu: &mut Screens, screen_h: i32) {
+    if let Some(Elements::Label(error)) = old.elements.get("error") {
+        menu.show_error(screen_h, &error.text);
+    }
+    let (Some(Elements::Panel(old_panel)), Screens::MainMenu(screen)) = (old.elements.get("random_panel"), &mut *menu) else {
+        return;
+    };
+    let Some(Elements::Panel(panel)) = screen.elements.get_mut("random_panel") else {
+        return;
+    };
+    for id in ["seed", "width", "height"] {
+        if let (Some(Elements::TextInput(old_field)), Some(Elements::TextInput(field))) =
+            (old_panel.elements.get(id), panel.elements.get_mut(id))
+        {
+            field.text = old_field.text.clone();
+            field.focused = old_field.focused;
         }
     }
-    None
+    if let (Some(Elements::Button(old_algorithm)), Some(Elements::Button(algorithm))) =
+        (old_panel.elements.get("algorithm"), panel.elements.get_mut("algorithm"))
+    {
+        algorithm.text = old_algorithm.text.clone();
+    }
 }
 
 pub trait StateHandler {
@@ -290,13 +326,18 @@ impl StateHandler for AppState {
                 screen.update(window);
 
                 // 1) Level selection buttons inside a panel "levels_panel"
+                let mut scroll_by = 0;
                 if let Some(Elements::Panel(panel)) = screen.elements.get_mut("levels_panel") {
+                    let wheel = window.get_mouse_wheel_move();
+                    if wheel != 0.0 && panel.rect.check_collision_point_rec(window.get_mouse_position()) {
+                        scroll_by = if wheel > 0.0 { -1 } else { 1 };
+                    }
                     for (id, element) in panel.elements.iter_mut() {
                         if let Elements::Button(btn) = element {
                             if btn.clicked {
                                 if let Some(index_str) = id.strip_prefix("level_") {
                                     if let Ok(index) = index_str.parse::<usize>() {
-                                        if index < self.mazes.len() {
+                                        if index < self.file_levels {
                                             self.current_level = index;
                                         }
                                     }
@@ -306,6 +347,48 @@ impl StateHandler for AppState {
                     }
                 }
 
+                // Random maze controls inside "random_panel"
+                let mut random_level = None;
+                if let Some(Elements::Panel(panel)) = screen.elements.get_mut("random_panel") {
+                    // Algorithm button cycles; its label is the selected algorithm
+                    let mut algorithm = Algorithm::Backtracker;
+                    if let Some(Elements::Button(btn)) = panel.elements.get_mut("algorithm") {
+                        let label = btn.text.clone().unwrap_or_default();
+                        algorithm = Algorithm::ALL.into_iter().find(|a| a.name() == label).unwrap_or(algorithm);
+                        if btn.clicked {
+                            algorithm = algorithm.next();
+                            btn.text = Some(algorithm.name().to_string());
+                        }
+                    }
+                    let random_clicked = matches!(panel.elements.get("random"), Some(Elements::Button(btn)) if btn.clicked);
+                    if random_clicked && let Some(Elements::TextInput(field)) = panel.elements.get_mut("seed") {
+                        // an empty field picks a seed and shows it, so the maze can be replayed
+                        if field.text.trim().is_empty() {
+                            field.text = unsafe { raylib::ffi::GetRandomValue(0, i32::MAX) }.to_string();
+                        }
+                        let mut config = GeneratorConfig { seed: rng::seed_from_text(&field.text), algorithm, ..GeneratorConfig::default() };
+                        // empty size fields keep the default size; anything but a
+                        // number counts as 0, which the generator turns down
+                        for (id, size) in [("width", &mut config.width), ("height", &mut config.height)] {
+                            if let Some(Elements::TextInput(field)) = panel.elements.get(id)
+                                && !field.text.trim().is_empty()
+                            {
+                                *size = field.text.trim().parse().unwrap_or(0);
+                            }
+                        }
+                        random_level = Some(config);
+                    }
+                }
+                if let Some(config) = random_level {
+                    self.load_random_level(&config);
+                    self.start_level(window);
+                    return;
+                }
+                if scroll_by != 0 {
+                    self.scroll_levels(scroll_by);
+                    return;
+                }
+
                 // 2) Play button
                 if let Some(Elements::Button(play_btn)) = screen.elements.get_mut("play") {
                     if play_btn.clicked {
@@ -329,7 +412,18 @@ impl StateHandler for AppState {
                 screen.update(window);
                 let dtt =  window.get_frame_time() as f64;
                 self.remaining_cooldown -= dtt;
-                self.animation_left -= dtt;
+                self.locked_message_left -= dtt;
+
+                // F2 cycles the render scale, F3 the filter, F4 dynamic resolution
+                if window.is_key_pressed(KeyboardKey::KEY_F2) {
+                    self.render.scale = self.render.scale.next();
+                }
+                if window.is_key_pressed(KeyboardKey::KEY_F3) {
+                    self.render.filter = self.render.filter.toggled();
+                }
+                if window.is_key_pressed(KeyboardKey::KEY_F4) {
+                    self.render.dynamic = !self.render.dynamic;
+                }
 
                 // ESC toggles pause and cursor
                 if window.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
@@ -353,27 +447,28 @@ impl StateHandler for AppState {
                 }
 
                 if !self.paused && self.is_playing {
-                    let maze = self.current_maze().clone();
-                    let capture_mouse = !self.enabled_cursor;
-
-                    // Move player with keyboard/mouse
-                    self.player.process_events(window, &maze, self.block_size);
-
-                    // Move enemies
-                    let dt = window.get_time();
-                    self.update_enemies(dt);
-
-                    // Enemy collisions
-                    self.check_enemy_collisions(window.get_time());
+                    // Only translate input here, the world does the rest
+                    let input = read_input(window);
+                    let world = self.world.as_mut().expect("no level has been started");
+                    let events = self.stepper.advance(world, &input, dtt);
+                    if events.player_hit {
+                        self.hit_sound = events.hit_sound;
+                        self.remaining_cooldown = world.hit_cooldown;
+                    }
+                    if let Some(color) = events.door_locked {
+                        self.locked_color = color;
+                        self.locked_message_left = 2.0;
+                    }
+                    let outcome = world.outcome;
 
                     // Check win/lose
-                    if self.is_on_goal() {
+                    if outcome == Outcome::Won {
                         self.is_playing = false;
                         self.enabled_cursor = true;
                         window.enable_cursor();
                         window.show_cursor();
                         self.current_screen = Screens::victory(self.width, self.height);
-                    } else if self.player.health == 0 {
+                    } else if outcome == Outcome::Lost {
                         self.is_playing = false;
                         self.enabled_cursor = true;
                         window.enable_cursor();
@@ -434,7 +529,7 @@ impl StateHandler for AppState {
                         } else if let Some(Elements::Button(menu_btn)) = pnl.elements.get_mut("pause_menu") {
                             if menu_btn.clicked {
                                 self.is_playing = false;
-                                self.current_screen = Screens::main_menu(self.width, self.height);
+                                self.current_screen = self.main_menu();
                             } else if let Some(Elements::Button(quit_btn)) = pnl.elements.get_mut("pause_quit") {
                                 if quit_btn.clicked {
                                     self.close_window = true
@@ -457,14 +552,15 @@ impl StateHandler for AppState {
                     // Next level
                     if let Some(Elements::Button(next_btn)) = panel.elements.get_mut("victory_next") {
                         if next_btn.clicked {
-                            // Advance level if possible
-                            if self.current_level + 1 < self.mazes.len() {
+                            // Levels play pack after pack; after the last one (or a
+                            // random maze) there is nothing left, back to the menu
+                            if self.current_level + 1 < self.file_levels {
                                 self.current_level += 1;
+                                self.start_level(window);
                             } else {
-                                self.current_level = 0;
+                                self.is_playing = false;
+                                self.current_screen = self.main_menu();
                             }
-                            // Start the selected level
-                            self.start_level(window);
                             return;
                         }
                     }
@@ -485,7 +581,7 @@ impl StateHandler for AppState {
                             self.enabled_cursor = true;
                             window.enable_cursor();
                             window.show_cursor();
-                            self.current_screen = Screens::main_menu(self.width, self.height);
+                            self.current_screen = self.main_menu();
                             return;
                         }
                     }
@@ -517,7 +613,7 @@ impl StateHandler for AppState {
                             self.enabled_cursor = true;
                             window.enable_cursor();
                             window.show_cursor();
-                            self.current_screen = Screens::main_menu(self.width, self.height);
+                            self.current_screen = self.main_menu();
                             return;
                         }
                     }
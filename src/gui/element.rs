@@ -1,6 +1,6 @@
 use raylib::prelude::*;
 
-use crate::gui::{Screen, button::Button, label::Label, panel::Panel, screens::Screens};
+use crate::gui::{Screen, button::Button, label::Label, panel::Panel, screens::Screens, text_input::TextInput};
 
 
 pub enum Elements {
@@ -8,6 +8,7 @@ pub enum Elements {
     Label(Label),
     Panel(Panel),
     Screen(Screen),
+    TextInput(TextInput),
 }
 
 pub trait Element {
@@ -22,6 +23,7 @@ impl Element for Elements {
             Elements::Label(tb) => tb.draw_element(d),
             Elements::Panel(p) => p.draw_element(d),
             Elements::Screen(s) => s.draw_element(d),
+            Elements::TextInput(t) => t.draw_element(d),
         }
     }
     fn update(&mut self, window: &mut RaylibHandle) {
@@ -30,6 +32,7 @@ impl Element for Elements {
             Elements::Label(tb) => tb.update(window),
             Elements::Panel(p) => p.update(window),
             Elements::Screen(s) => s.update(window),
+            Elements::TextInput(t) => t.update(window),
         }
     }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ mod button;
 mod screen;
 mod label;
 mod panel;
+mod text_input;
 pub mod screens;
 
 pub use crate::gui::screen::*;
\ No newline at end of file
@@ -2,7 +2,16 @@ use std::collections::HashMap;
 
 use raylib::prelude::*;
 
-use crate::gui::{button::Button, element::{Element, Elements}, label::Label, panel::Panel, *};
+use crate::gui::{button::Button, element::{Element, Elements}, label::Label, panel::Panel, text_input::TextInput, *};
+use crate::maze::generator::{Algorithm, GeneratorConfig};
+/// One row of the main menu level list.
+pub enum LevelRow {
+    /// Pack heading
+    Pack(String),
+    /// Level index and name
+    Level(usize, String),
+}
+
 pub enum Screens {
     MainMenu(Screen),
     Game(Screen),
@@ -21,60 +30,113 @@ impl Screens {
             Screens::Defeat(sc) => sc.draw_element(d),
         }
     }
-    pub fn main_menu(screen_w: i32, screen_h: i32) -> Self {
+    /// Shows an error message at the bottom of the main menu, only the first lines fit.
+    pub fn show_error(&mut self, screen_h: i32, message: &str) {
+        if let Screens::MainMenu(screen) = self {
+            let text: Vec<&str> = message.lines().take(4).collect();
+            let label = Label::new(
+                text.join("\n"),
+                Vector2 { x: 20.0, y: (screen_h - 70) as f32 },
+                16,
+                Color::RED,
+            );
+            screen.elements.insert("error".to_string(), Elements::Label(label));
+        }
+    }
+    /// Level list rows that fit in the main menu panel; the rest scroll.
+    pub const VISIBLE_LEVEL_ROWS: usize = 4;
+
+    /// Main menu: scrolling level list, random maze controls, play and quit.
+    /// `selected` is the index of the highlighted level button.
+    pub fn main_menu(screen_w: i32, screen_h: i32, rows: &[LevelRow], scroll: usize, selected: usize) -> Self {
         let mut elements = HashMap::new();
 
         // Title label
         let title = Label::new(
             "Maze Raycaster",
-            Vector2 { x: (screen_w / 2 - 150) as f32, y: 80.0 },
+            Vector2 { x: (screen_w / 2 - 150) as f32, y: 40.0 },
             40,
             Color::WHITE,
         );
         elements.insert("title".to_string(), Elements::Label(title));
 
-        // Panel for level buttons
-        let panel_rect = Rectangle {
-            x: (screen_w / 2 - 150) as f32,
-            y: 160.0,
-            width: 300.0,
-            height: 200.0,
-        };
-        let mut level_panel = Panel::new(panel_rect, Some(Color::DARKGRAY));
+        let level_panel = Screens::level_panel(screen_w, rows, scroll, selected);
+        let level_rect = level_panel.rect;
+        elements.insert("levels_panel".to_string(), Elements::Panel(level_panel));
 
-        // Example: 3 levels
-        let level_names = ["Level 1", "Level 2", "Level 3"];
         let button_height = 40.0;
         let spacing = 10.0;
+
+        // Random maze: button plus the seed it is generated from
+        let panel_rect = Rectangle {
+            x: level_rect.x,
+            y: level_rect.y + level_rect.height + spacing,
+            width: level_rect.width,
+            height: 20.0 + 2.0 * (button_height + spacing),
+        };
+        let mut random_panel = Panel::new(panel_rect, Some(Color::DARKGRAY));
         let mut y = panel_rect.y + 20.0;
 
-        for (i, name) in level_names.iter().enumerate() {
+        let random_rect = Rectangle {
+            x: panel_rect.x + 20.0,
+            y,
+            width: 140.0,
+            height: button_height,
+        };
+        let random_btn = Button::new(
+            random_rect,
+            Some("Random maze".to_string()),
+            Color::DARKPURPLE,
+            Color::PURPLE,
+        );
+        random_panel.add_element("random", Elements::Button(random_btn));
+
+        let seed_rect = Rectangle {
+            x: random_rect.x + random_rect.width + 10.0,
+            y,
+            width: panel_rect.width - 40.0 - random_rect.width - 10.0,
+            height: button_height,
+        };
+        let seed_field = TextInput::new(seed_rect, "seed", 10, Color::DARKGRAY, Color::GRAY);
+        random_panel.add_element("seed", Elements::TextInput(seed_field));
+        y += button_height + spacing;
+
+        // Cycles through the generator algorithms
+        let algorithm_rect = Rectangle {
+            x: panel_rect.x + 20.0,
+            y,
+            width: 140.0,
+            height: button_height,
+        };
+        let algorithm_btn = Button::new(
+            algorithm_rect,
+            Some(Algorithm::Backtracker.name().to_string()),
+            Color::GRAY,
+            Color::LIGHTGRAY,
+        );
+        random_panel.add_element("algorithm", Elements::Button(algorithm_btn));
+
+        // maze size in cells, empty for the default
+        let defaults = GeneratorConfig::default();
+        let size_width = (panel_rect.width - 40.0 - algorithm_rect.width - 20.0) / 2.0;
+        for (k, (id, default)) in [("width", defaults.width), ("height", defaults.height)].into_iter().enumerate() {
             let rect = Rectangle {
-                x: panel_rect.x + 20.0,
+                x: algorithm_rect.x + algorithm_rect.width + 10.0 + k as f32 * (size_width + 10.0),
                 y,
-                width: panel_rect.width - 40.0,
+                width: size_width,
                 height: button_height,
             };
-
-            let btn = Button::new(
-                rect,
-                Some(name.to_string()),
-                Color::GRAY,
-                Color::LIGHTGRAY,
-            );
-
-            level_panel.add_element(format!("level_{}", i), Elements::Button(btn));
-
-            y += button_height + spacing;
+            let field = TextInput::new(rect, format!("{} {}", &id[..1], default), 2, Color::DARKGRAY, Color::GRAY);
+            random_panel.add_element(id, Elements::TextInput(field));
         }
 
-        elements.insert("levels_panel".to_string(), Elements::Panel(level_panel));
+        elements.insert("random_panel".to_string(), Elements::Panel(random_panel));
 
-        // Play button
+        // Play and quit side by side, leaving the bottom for error messages
         let play_rect = Rectangle {
-            x: (screen_w / 2 - 100) as f32,
-            y: panel_rect.y + panel_rect.height + 40.0,
-            width: 200.0,
+            x: (screen_w / 2 - 150) as f32,
+            y: panel_rect.y + panel_rect.height + 15.0,
+            width: 140.0,
             height: 50.0,
         };
         let play_button = Button::new(
@@ -87,9 +149,9 @@ impl Screens {
 
         // Quit button
         let quit_rect = Rectangle {
-            x: (screen_w / 2 - 100) as f32,
-            y: play_rect.y + 70.0,
-            width: 200.0,
+            x: (screen_w / 2 + 10) as f32,
+            y: play_rect.y,
+            width: 140.0,
             height: 50.0,
         };
         let quit_button = Button::new(
@@ -105,6 +167,64 @@ impl Screens {
             elements,
         })
     }
+
+    /// Panel showing `VISIBLE_LEVEL_ROWS` rows of the level list starting at `scroll`.
+    /// Level buttons get ids `level_{index}` with the index into the level list.
+    pub fn level_panel(screen_w: i32, rows: &[LevelRow], scroll: usize, selected: usize) -> Panel {
+        let button_height = 40.0;
+        let spacing = 10.0;
+        let panel_rect = Rectangle {
+            x: (screen_w / 2 - 150) as f32,
+            y: 100.0,
+            width: 300.0,
+            height: 20.0 + Screens::VISIBLE_LEVEL_ROWS as f32 * (button_height + spacing),
+        };
+        let mut panel = Panel::new(panel_rect, Some(Color::DARKGRAY));
+        let mut y = panel_rect.y + 20.0;
+
+        for (i, row) in rows.iter().enumerate().skip(scroll).take(Screens::VISIBLE_LEVEL_ROWS) {
+            match row {
+                LevelRow::Pack(name) => {
+                    let label = Label::new(
+                        name.clone(),
+                        Vector2 { x: panel_rect.x + 20.0, y: y + 10.0 },
+                        20,
+                        Color::GOLD,
+                    );
+                    panel.add_element(format!("pack_{}", i), Elements::Label(label));
+                }
+                LevelRow::Level(index, name) => {
+                    let rect = Rectangle {
+                        x: panel_rect.x + 20.0,
+                        y,
+                        width: panel_rect.width - 40.0,
+                        height: button_height,
+                    };
+                    let mut btn = Button::new(
+                        rect,
+                        Some(name.clone()),
+                        Color::GRAY,
+                        Color::LIGHTGRAY,
+                    );
+                    btn.selected = *index == selected;
+                    panel.add_element(format!("level_{}", index), Elements::Button(btn));
+                }
+            }
+            y += button_height + spacing;
+        }
+
+        // hints that the list scrolls
+        let arrow_x = panel_rect.x + panel_rect.width - 16.0;
+        if scroll > 0 {
+            let up = Label::new("^", Vector2 { x: arrow_x, y: panel_rect.y + 2.0 }, 16, Color::WHITE);
+            panel.add_element("scroll_up", Elements::Label(up));
+        }
+        if scroll + Screens::VISIBLE_LEVEL_ROWS < rows.len() {
+            let down = Label::new("v", Vector2 { x: arrow_x, y: panel_rect.y + panel_rect.height - 18.0 }, 16, Color::WHITE);
+            panel.add_element("scroll_down", Elements::Label(down));
+        }
+        panel
+    }
     pub fn game(screen_w: i32, _screen_h: i32) -> Self {
         let mut elements = HashMap::new();
 
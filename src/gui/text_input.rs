@@ -0,0 +1,84 @@
+use raylib::prelude::*;
+
+use crate::gui::element::Element;
+
+
+pub struct TextInput {
+    pub rect: Rectangle,
+    pub text: String,
+    /// Greyed out hint shown while the field is empty
+    pub placeholder: String,
+    pub max_len: usize,
+    pub focused: bool,
+    pub color: Color,
+    pub focus_color: Color,
+}
+
+impl TextInput {
+    pub fn new(rect: Rectangle, placeholder: impl Into<String>, max_len: usize, color: Color, focus_color: Color) -> Self {
+        TextInput {
+            rect,
+            text: String::new(),
+            placeholder: placeholder.into(),
+            max_len,
+            focused: false,
+            color,
+            focus_color,
+        }
+    }
+}
+
+impl Element for TextInput {
+    /// Click to focus, click elsewhere to leave; typed characters go in while focused.
+    fn update(&mut self, window: &mut RaylibHandle) {
+        let mouse = window.get_mouse_position();
+        let hovered = mouse.x >= self.rect.x
+            && mouse.x <= self.rect.x + self.rect.width
+            && mouse.y >= self.rect.y
+            && mouse.y <= self.rect.y + self.rect.height;
+
+        if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.focused = hovered;
+        }
+        if !self.focused {
+            return;
+        }
+
+        while let Some(ch) = window.get_char_pressed() {
+            if !ch.is_control() && self.text.chars().count() < self.max_len {
+                self.text.push(ch);
+            }
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || window.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE) {
+            self.text.pop();
+        }
+    }
+
+    fn draw_element(&self, d: &mut RaylibDrawHandle) {
+        let fill_color = if self.focused { self.focus_color } else { self.color };
+        d.draw_rectangle_rec(self.rect, fill_color);
+        d.draw_rectangle_lines(
+            self.rect.x as i32,
+            self.rect.y as i32,
+            self.rect.width as i32,
+            self.rect.height as i32,
+            Color::WHITE,
+        );
+
+        let font_size = 20;
+        let (text, color) = if self.text.is_empty() && !self.focused {
+            (self.placeholder.as_str(), Color::LIGHTGRAY)
+        } else {
+            (self.text.as_str(), Color::WHITE)
+        };
+        let x = self.rect.x + 8.0;
+        let y = self.rect.y + (self.rect.height - font_size as f32) / 2.0;
+        d.draw_text(text, x as i32, y as i32, font_size, color);
+
+        // blinking caret after the text
+        if self.focused && (d.get_time() * 2.0) as i64 % 2 == 0 {
+            let caret_x = x as i32 + d.measure_text(&self.text, font_size) + 2;
+            d.draw_text("_", caret_x, y as i32, font_size, Color::WHITE);
+        }
+    }
+}
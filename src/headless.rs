@@ -0,0 +1,425 @@
+//! Rendering without a window: `maze --render` writes level previews to image files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raylib::prelude::Vector2;
+
+use crate::framebuffer::{self, Framebuffer};
+use crate::level::{Level, load_level};
+use crate::packs;
+use crate::render_scene;
+use crate::sim::{self, World};
+use crate::textures::{self, TextureManager};
+
+pub const USAGE: &str = "\
+usage: maze --render [options] [level files...]
+
+Renders each level from its start (or from --pose) and writes one image per
+level. Without level files every level of the packs in levels/ is rendered.
+
+options:
+  --out <dir>          where to write the images (default: previews)
+  --size <w>x<h>       image size in pixels (default: 900x600)
+  --pose <x>,<y>,<deg> camera position in blocks and angle in degrees,
+                       0 faces east, 90 south (default: the level start)
+  --format <png|ppm>   image format (default: png)
+  --textures <file>    texture manifest (default: assets/textures.txt)";
+
+/// Where the camera stands, in blocks, and where it looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub x: f32,
+    pub y: f32,
+    /// Radians; written in degrees on the command line
+    pub angle: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderArgs {
+    /// Empty means every level of the packs
+    pub levels: Vec<PathBuf>,
+    pub out: PathBuf,
+    pub size: (u32, u32),
+    pub pose: Option<Pose>,
+    /// File extension, "png" or "ppm"
+    pub format: String,
+    pub manifest: String,
+}
+
+impl Default for RenderArgs {
+    fn default() -> Self {
+        RenderArgs {
+            levels: vec![],
+            out: PathBuf::from("previews"),
+            size: (900, 600),
+            pose: None,
+            format: "png".to_string(),
+            manifest: textures::TEXTURE_MANIFEST.to_string(),
+        }
+    }
+}
+
+/// Parses the arguments after `--render`.
+pub fn parse_args(args: &[String]) -> Result<RenderArgs, String> {
+    let mut parsed = RenderArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            parsed.levels.push(PathBuf::from(arg));
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))?;
+        match arg.as_str() {
+            "--out" => parsed.out = PathBuf::from(value),
+            "--size" => {
+                let size = value.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                match size {
+                    Some((w, h)) if w > 0 && h > 0 => parsed.size = (w, h),
+                    _ => return Err(format!("size `{}` must be <width>x<height>", value)),
+                }
+            }
+            "--pose" => {
+                let numbers: Vec<Option<f32>> = value.split(',').map(|n| n.trim().parse().ok()).collect();
+                match numbers.as_slice() {
+                    [Some(x), Some(y), Some(deg)] => parsed.pose = Some(Pose { x: *x, y: *y, angle: deg.to_radians() }),
+                    _ => return Err(format!("pose `{}` must be <x>,<y>,<degrees>", value)),
+                }
+            }
+            "--format" => match value.as_str() {
+                "png" | "ppm" => parsed.format = value.clone(),
+                _ => return Err(format!("format `{}` must be png or ppm", value)),
+            },
+            "--textures" => parsed.manifest = value.clone(),
+            _ => return Err(format!("unknown option `{}`\n\n{}", arg, USAGE)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Renders `level` as the game would show it right after it starts: enemies
+/// and items at their spawns, seen from the start or from `pose`.
+pub fn render_level(
+    level: &Level,
+    pose: Option<Pose>,
+    (width, height): (u32, u32),
+    block_size: usize,
+    texture_manager: &TextureManager,
+) -> Framebuffer {
+    // the seed only matters once the enemies move
+    let world = World::new(level, block_size as f32, sim::MAX_HEALTH, 0);
+    let mut player = world.player.clone();
+    if let Some(pose) = pose {
+        player.pos = Vector2::new(pose.x * block_size as f32, pose.y * block_size as f32);
+        player.a = pose.angle;
+    }
+
+    let mut framebuffer = Framebuffer::new(width, height, framebuffer::BACKGROUND_COLOR);
+    framebuffer.fog = level.fog;
+    framebuffer.clear();
+    render_scene(&mut framebuffer, &player, &world.maze, &world.enemies, &world.items, block_size, texture_manager);
+    framebuffer
+}
+
+/// Image name for a level file: its pack directory and file name, e.g. `classic-maze1`.
+fn preview_name(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.parent().and_then(Path::file_name) {
+        Some(pack) => format!("{}-{}", pack.to_string_lossy(), stem),
+        None => stem.into_owned(),
+    }
+}
+
+/// Runs `maze --render`. A level that fails to load or save is reported and
+/// skipped; the error at the end says how many did.
+pub fn run(args: &[String], block_size: usize) -> Result<(), String> {
+    let args = parse_args(args)?;
+    let texture_manager = match TextureManager::load_images(&args.manifest) {
+        Ok(texture_manager) => {
+            for err in &texture_manager.missing {
+                eprintln!("{}, using a placeholder", err);
+            }
+            texture_manager
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            TextureManager::empty()
+        }
+    };
+    let levels = if args.levels.is_empty() {
+        packs::discover_packs().into_iter().flat_map(|pack| pack.levels).collect()
+    } else {
+        args.levels.clone()
+    };
+    if levels.is_empty() {
+        return Err(format!("no levels found in {}/", packs::LEVELS_DIR));
+    }
+    fs::create_dir_all(&args.out).map_err(|err| format!("{}: {}", args.out.display(), err))?;
+
+    let mut failed = 0;
+    for path in &levels {
+        let level = match load_level(&path.to_string_lossy()) {
+            Ok(level) => level,
+            Err(err) => {
+                eprintln!("{}", err);
+                failed += 1;
+                continue;
+            }
+        };
+        let framebuffer = render_level(&level, args.pose, args.size, block_size, &texture_manager);
+        let file = args.out.join(format!("{}.{}", preview_name(path), args.format));
+        match framebuffer.save(&file) {
+            Ok(()) => println!("{}", file.display()),
+            Err(err) => {
+                eprintln!("{}", err);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} levels could not be rendered", failed, levels.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //! Golden images: fixed camera poses in the built-in levels, rendered with
+    //! flat colors (no texture files) and compared with the PPMs in
+    //! `tests/golden`. Run with `UPDATE_GOLDEN=1` to rewrite them after an
+    //! intended change to the renderer. A failure writes the actual image and a
+    //! diff to `target/golden` for inspection.
+
+    use std::env;
+    use std::f32::consts::PI;
+
+    use raylib::prelude::Color;
+
+    use super::*;
+
+    const GOLDEN_DIR: &str = "tests/golden";
+    const FAILURE_DIR: &str = "target/golden";
+    const SIZE: (u32, u32) = (128, 80);
+    const BLOCK: usize = 100;
+    /// A pixel matches if no channel is further off than this
+    const CHANNEL_TOLERANCE: u8 = 3;
+    /// Pixels allowed past the tolerance, for float rounding along edges
+    const MAX_BAD_PIXELS: usize = (SIZE.0 * SIZE.1 / 500) as usize;
+
+    struct Ppm {
+        width: u32,
+        height: u32,
+        rgb: Vec<u8>,
+    }
+
+    /// Reads a binary PPM as written by `Framebuffer::write_ppm`.
+    fn read_ppm(path: &Path) -> Result<Ppm, String> {
+        let data = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        // magic, width, height and max value, each followed by one whitespace byte
+        let mut fields = Vec::new();
+        let mut start = 0;
+        for (i, byte) in data.iter().enumerate() {
+            if byte.is_ascii_whitespace() {
+                fields.push(String::from_utf8_lossy(&data[start..i]).into_owned());
+                start = i + 1;
+                if fields.len() == 4 {
+                    break;
+                }
+            }
+        }
+        let [magic, width, height, max] = fields.as_slice() else {
+            return Err(format!("{}: truncated header", path.display()));
+        };
+        let (Ok(width), Ok(height)) = (width.parse::<u32>(), height.parse::<u32>()) else {
+            return Err(format!("{}: bad size", path.display()));
+        };
+        if magic != "P6" || max != "255" || data.len() - start != (width * height * 3) as usize {
+            return Err(format!("{}: not an 8-bit P6 image of {}x{}", path.display(), width, height));
+        }
+        Ok(Ppm { width, height, rgb: data[start..].to_vec() })
+    }
+
+    fn to_ppm(framebuffer: &Framebuffer) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        framebuffer.write_ppm(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Renders `level_file` without textures from the pose picked for it and
+    /// compares the result with `tests/golden/<name>.ppm`.
+    fn check(name: &str, level_file: &str, pose: impl FnOnce(&Level) -> Pose) {
+        check_with(name, level_file, pose, &TextureManager::empty());
+    }
+
+    /// `check` with the textures in `texture_manager`.
+    fn check_with(name: &str, level_file: &str, pose: impl FnOnce(&Level) -> Pose, texture_manager: &TextureManager) {
+        let level = load_level(level_file).unwrap_or_else(|err| panic!("{}", err));
+        let framebuffer = render_level(&level, Some(pose(&level)), SIZE, BLOCK, texture_manager);
+        let golden_path = Path::new(GOLDEN_DIR).join(format!("{}.ppm", name));
+
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(GOLDEN_DIR).unwrap();
+            fs::write(&golden_path, to_ppm(&framebuffer)).unwrap();
+            return;
+        }
+        let golden = read_ppm(&golden_path).unwrap_or_else(|err| panic!("{} (UPDATE_GOLDEN=1 creates it)", err));
+        assert_eq!((golden.width, golden.height), SIZE, "{}: golden image has the wrong size", name);
+
+        // the diff shows bad pixels in red over a dimmed copy of the golden image
+        let mut diff = Framebuffer::new(SIZE.0, SIZE.1, Color::BLACK);
+        let mut bad = 0;
+        let mut worst = 0;
+        for y in 0..SIZE.1 {
+            for x in 0..SIZE.0 {
+                let i = ((y * SIZE.0 + x) * 3) as usize;
+                let expected = &golden.rgb[i..i + 3];
+                let actual = framebuffer.get_color(x, y);
+                let off = [actual.r, actual.g, actual.b].iter().zip(expected).map(|(a, e)| a.abs_diff(*e)).max().unwrap();
+                worst = worst.max(off);
+                if off > CHANNEL_TOLERANCE {
+                    bad += 1;
+                    diff.set_current_color(Color::RED);
+                } else {
+                    diff.set_current_color(Color::new(expected[0] / 3, expected[1] / 3, expected[2] / 3, 255));
+                }
+                diff.set_pixel(x, y, 0.0);
+            }
+        }
+        if bad > MAX_BAD_PIXELS {
+            fs::create_dir_all(FAILURE_DIR).unwrap();
+            let actual_path = Path::new(FAILURE_DIR).join(format!("{}-actual.ppm", name));
+            let diff_path = Path::new(FAILURE_DIR).join(format!("{}-diff.ppm", name));
+            fs::write(&actual_path, to_ppm(&framebuffer)).unwrap();
+            fs::write(&diff_path, to_ppm(&diff)).unwrap();
+            panic!(
+                "{}: {} pixels differ by more than {} (worst {}), see {} and {}",
+                name,
+                bad,
+                CHANNEL_TOLERANCE,
+                worst,
+                actual_path.display(),
+                diff_path.display(),
+            );
+        }
+    }
+
+    /// The checkerboard placeholder under every id in the texture manifest. It is
+    /// made in memory, so the result doesn't depend on image files or decoders.
+    fn checkerboards() -> TextureManager {
+        let manifest = fs::read_to_string(textures::TEXTURE_MANIFEST).unwrap();
+        let mut texture_manager = TextureManager::empty();
+        for entry in textures::parse_manifest(&manifest).unwrap() {
+            texture_manager.images.insert(entry.id, textures::placeholder());
+        }
+        texture_manager
+    }
+
+    /// Only the shipped floor and ceiling images, read through `textures::load_image`
+    /// as the game reads them. The sprite sheets are left out, so nothing else
+    /// changes if they get redrawn.
+    fn plane_textures() -> TextureManager {
+        let mut texture_manager = TextureManager::empty();
+        for (id, file) in [('T', "assets/floor_tiles.png"), ('W', "assets/ceiling_wood.png")] {
+            texture_manager.images.insert(id, textures::load_image(id, file).unwrap_or_else(|err| panic!("{}", err)));
+        }
+        texture_manager
+    }
+
+    /// The level start, turned by `turn` radians.
+    fn from_start(turn: f32) -> impl FnOnce(&Level) -> Pose {
+        move |level| {
+            let player = World::new(level, BLOCK as f32, sim::MAX_HEALTH, 0).player;
+            Pose { x: player.pos.x / BLOCK as f32, y: player.pos.y / BLOCK as f32, angle: player.a + turn }
+        }
+    }
+
+    /// Two blocks back from `cell` along a free row or column, looking at it,
+    /// so whatever stands in the cell fills the middle of the view.
+    fn facing(cell: impl Fn(&Level) -> (usize, usize)) -> impl FnOnce(&Level) -> Pose {
+        move |level| {
+            let (i, j) = cell(level);
+            let free = |di: isize, dj: isize| {
+                (1..=2).all(|k| {
+                    let (x, y) = (i as isize + di * k, j as isize + dj * k);
+                    x >= 0 && y >= 0 && level.maze.get(x as usize, y as usize).is_some_and(|c| c.is_walkable())
+                })
+            };
+            let (di, dj) = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .into_iter()
+                .find(|&(di, dj)| free(di, dj))
+                .expect("no free line of sight two blocks long");
+            Pose {
+                x: i as f32 + 0.5 + 2.0 * di as f32,
+                y: j as f32 + 0.5 + 2.0 * dj as f32,
+                angle: (-dj as f32).atan2(-di as f32),
+            }
+        }
+    }
+
+    #[test]
+    fn maze1_start() {
+        check("maze1_start", "levels/classic/maze1.txt", from_start(0.0));
+    }
+
+    #[test]
+    fn maze1_turned() {
+        check("maze1_turned", "levels/classic/maze1.txt", from_start(PI / 2.0));
+    }
+
+    #[test]
+    fn maze2_start() {
+        check("maze2_start", "levels/classic/maze2.txt", from_start(0.0));
+    }
+
+    #[test]
+    fn maze2_turned() {
+        check("maze2_turned", "levels/classic/maze2.txt", from_start(PI));
+    }
+
+    #[test]
+    fn maze3_start() {
+        check("maze3_start", "levels/classic/maze3.txt", from_start(0.0));
+    }
+
+    #[test]
+    fn maze3_turned() {
+        check("maze3_turned", "levels/classic/maze3.txt", from_start(-PI / 2.0));
+    }
+
+    #[test]
+    fn maze1_enemy() {
+        check("maze1_enemy", "levels/classic/maze1.txt", facing(|level| level.enemies[0].cell));
+    }
+
+    #[test]
+    fn doors_item() {
+        check("doors_item", "levels/showcase/doors.txt", facing(|level| level.items[0].cell));
+    }
+
+    #[test]
+    fn maze1_enemy_textured() {
+        let pose = facing(|level| level.enemies[0].cell);
+        check_with("maze1_enemy_textured", "levels/classic/maze1.txt", pose, &checkerboards());
+    }
+
+    #[test]
+    fn tiles_floor_and_ceiling() {
+        check_with("tiles_floor_and_ceiling", "levels/showcase/tiles.txt", from_start(0.0), &plane_textures());
+    }
+
+    #[test]
+    fn ppm_round_trips() {
+        let level = load_level("levels/classic/maze1.txt").unwrap();
+        let framebuffer = render_level(&level, None, SIZE, BLOCK, &TextureManager::empty());
+        // one file per test run, so runs in parallel don't read each other's
+        let path = env::temp_dir().join(format!("maze_round_trip_{}.ppm", std::process::id()));
+        framebuffer.save(&path).unwrap();
+        let ppm = read_ppm(&path);
+        let _ = fs::remove_file(&path);
+        let ppm = ppm.unwrap();
+        assert_eq!((ppm.width, ppm.height), SIZE);
+        let color = framebuffer.get_color(5, 7);
+        let i = ((7 * SIZE.0 + 5) * 3) as usize;
+        assert_eq!(&ppm.rgb[i..i + 3], &[color.r, color.g, color.b]);
+    }
+}
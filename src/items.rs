@@ -0,0 +1,107 @@
+use raylib::prelude::{Color, Vector2};
+
+use crate::animation::Frame;
+use crate::maze::KeyColor;
+use crate::sprite::Sprite;
+
+/// What picking an item up does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// Restores health, never above the maximum
+    Heal(i32),
+    /// One more key for the locked doors of its color
+    Key(KeyColor),
+    /// Adds to the score
+    Score(u32),
+    /// Multiplies walking speed for a while
+    SpeedBoost { factor: f32, seconds: f32 },
+}
+
+/// One kind of collectible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemType {
+    /// Name used in level headers, e.g. `item = key 5 2`
+    pub name: &'static str,
+    /// Character that places it in a maze grid; also its texture key
+    pub symbol: char,
+    pub effect: Effect,
+    /// Sprite tint, or flat color when there is no texture
+    pub color: Color,
+    pub minimap_color: Color,
+}
+
+/// The item registry.
+pub const ITEM_TYPES: &[ItemType] = &[
+    ItemType {
+        name: "health",
+        symbol: 'h',
+        effect: Effect::Heal(1),
+        color: Color::GREEN,
+        minimap_color: Color::GREEN,
+    },
+    // one key per lock color: `L`, `R` and `U` in the grid
+    ItemType {
+        name: "key",
+        symbol: 'k',
+        effect: Effect::Key(KeyColor::Gold),
+        color: Color::GOLD,
+        minimap_color: Color::GOLD,
+    },
+    ItemType {
+        name: "red_key",
+        symbol: 'r',
+        effect: Effect::Key(KeyColor::Red),
+        color: Color::RED,
+        minimap_color: Color::RED,
+    },
+    ItemType {
+        name: "blue_key",
+        symbol: 'u',
+        effect: Effect::Key(KeyColor::Blue),
+        color: Color::BLUE,
+        minimap_color: Color::BLUE,
+    },
+    ItemType {
+        name: "gem",
+        symbol: '$',
+        effect: Effect::Score(10),
+        color: Color::SKYBLUE,
+        minimap_color: Color::BLUE,
+    },
+    ItemType {
+        name: "speed",
+        symbol: '>',
+        effect: Effect::SpeedBoost { factor: 1.6, seconds: 5.0 },
+        color: Color::MAGENTA,
+        minimap_color: Color::MAGENTA,
+    },
+];
+
+pub fn item_type(name: &str) -> Option<&'static ItemType> {
+    ITEM_TYPES.iter().find(|t| t.name == name)
+}
+
+pub fn item_for_symbol(symbol: char) -> Option<&'static ItemType> {
+    ITEM_TYPES.iter().find(|t| t.symbol == symbol)
+}
+
+/// Items are drawn smaller than enemies, standing on the floor.
+const ITEM_SCALE: f32 = 0.4;
+
+/// An item lying in the maze.
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub kind: &'static ItemType,
+    pub pos: Vector2,
+}
+
+impl Item {
+    pub fn sprite(&self) -> Sprite {
+        Sprite {
+            pos: self.pos,
+            frame: Frame::new(self.kind.symbol),
+            color: self.kind.color,
+            scale: ITEM_SCALE,
+        }
+    }
+}
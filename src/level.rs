@@ -0,0 +1,346 @@
+use std::f32::consts::PI;
+use std::fs;
+
+use raylib::prelude::Color;
+
+use crate::enemies;
+use crate::framebuffer::Fog;
+use crate::items::{self, Effect, ItemType};
+use crate::maze::{Cell, DEFAULT_CEILING, DEFAULT_FLOOR, Maze, MazeError, MazeProblem, ProblemKind, parse_maze, path};
+
+/// Used when a level doesn't name its own track.
+pub const DEFAULT_MUSIC: &str = "assets/video0.MP3";
+/// Matches the framebuffer background, so far walls fade into it.
+pub const FOG_COLOR: Color = Color::new(50, 50, 100, 255);
+/// Fog of a level that doesn't set one, with or without a header.
+pub const DEFAULT_FOG: Fog = Fog::linear(FOG_COLOR, 300.0, 1500.0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnemySpawn {
+    /// Name from `enemies::ENEMY_TYPES`
+    pub kind: String,
+    /// Grid cell, (column, row) counted from 0
+    pub cell: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemSpawn {
+    pub kind: &'static ItemType,
+    /// Grid cell, (column, row) counted from 0
+    pub cell: (usize, usize),
+}
+
+/// A playable level: the maze grid plus everything the header declares.
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub maze: Maze,
+    pub enemies: Vec<EnemySpawn>,
+    pub items: Vec<ItemSpawn>,
+    /// Radians; written in degrees in the file
+    pub start_angle: f32,
+    pub fog: Fog,
+    pub music: Option<String>,
+    /// Seconds to reach the goal, no limit if None
+    pub time_limit: Option<f32>,
+}
+
+impl Level {
+    /// Level with default settings around a maze that came without a header,
+    /// e.g. a generated one. Enemies are placed by `default_spawns`.
+    pub fn from_maze(name: impl Into<String>, maze: Maze) -> Self {
+        Level {
+            name: name.into(),
+            enemies: default_spawns(&maze),
+            items: vec![],
+            maze,
+            start_angle: PI / 4.0,
+            fog: DEFAULT_FOG,
+            music: None,
+            time_limit: None,
+        }
+    }
+
+    pub fn music(&self) -> &str {
+        self.music.as_deref().unwrap_or(DEFAULT_MUSIC)
+    }
+}
+
+/// One enemy halfway along the maze and one as far from the start as possible.
+pub fn default_spawns(maze: &Maze) -> Vec<EnemySpawn> {
+    let Some(start) = maze.find(Cell::Start) else { return vec![] };
+    let dist = path::distances_from(maze, start, |(x, y)| maze.get(x, y).is_some_and(Cell::is_passable));
+
+    let mut cells: Vec<(usize, usize, usize)> = maze.rows()
+        .enumerate()
+        .flat_map(|(j, row)| row.iter().enumerate().map(move |(i, &cell)| (i, j, cell)))
+        .filter(|&(_, _, cell)| cell.is_enemy_walkable() && cell != Cell::Goal)
+        .filter_map(|(i, j, _)| dist[j][i].map(|d| (i, j, d)))
+        .collect();
+    cells.sort_by_key(|&(i, j, d)| (d, j, i));
+
+    let Some(&(_, _, far)) = cells.last() else { return vec![] };
+    let half = cells.iter().find(|&&(_, _, d)| d >= far / 2).copied();
+    [half, cells.last().copied()]
+        .into_iter()
+        .flatten()
+        .map(|(i, j, _)| EnemySpawn { kind: enemies::default_type().name.to_string(), cell: (i, j) })
+        .collect()
+}
+
+/// Pulls item symbols (see `items::ITEM_TYPES`) out of a grid, leaving floor
+/// in their place.
+fn take_items(grid: &str) -> (String, Vec<ItemSpawn>) {
+    let mut items = Vec::new();
+    let lines: Vec<String> = grid
+        .lines()
+        .enumerate()
+        .map(|(j, line)| {
+            line.chars()
+                .enumerate()
+                .map(|(i, c)| match items::item_for_symbol(c) {
+                    Some(kind) => {
+                        items.push(ItemSpawn { kind, cell: (i, j) });
+                        ' '
+                    }
+                    None => c,
+                })
+                .collect()
+        })
+        .collect();
+    (lines.join("\n"), items)
+}
+
+/// A problem if locked doors keep the player from every goal with the keys
+/// lying in the level, see `path::winnable`. The grid starts after `first_line` lines of the file.
+fn check_keys(maze: &Maze, items: &[ItemSpawn], first_line: usize) -> Option<MazeProblem> {
+    let keys: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item.kind.effect {
+            Effect::Key(color) => Some((item.cell, color)),
+            _ => None,
+        })
+        .collect();
+    if path::winnable(maze, &keys) {
+        return None;
+    }
+    let position = maze.find(Cell::Start).map(|(i, j)| (first_line + j + 1, i + 1));
+    Some(MazeProblem { position, kind: ProblemKind::NotEnoughKeys })
+}
+
+/// Cuts a `#` comment off a header line. Only a `#` at the start of the line or
+/// after whitespace starts one, so values such as `music = songs/#1.ogg` stay whole.
+/// The texture manifest uses the same rule.
+pub fn strip_comment(line: &str) -> &str {
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return &line[..i];
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Parses a level file. The optional header comes first, one `key = value` per
+/// line, and ends with a `---` line; the maze grid follows. Keys left out get
+/// the defaults from `Level::from_maze`, and a file without a `---` line is just
+/// a grid with those defaults, except that it has no enemies. See `strip_comment`
+/// for comments.
+///
+/// ```text
+/// name = Level 1
+/// start_angle = 45            # degrees, 0 faces east, 90 south
+/// fog = linear 300 1500       # or: exp <start> <density>, or: off
+/// floor = _                   # texture ids from assets/textures.txt
+/// ceiling = ^
+/// music = assets/video0.MP3
+/// time_limit = 120            # seconds
+/// enemy = pumpkin 12 4        # type, column, line of the grid (from 1, as in an editor)
+/// enemy = brute 8 8          # any name from enemies::ENEMY_TYPES
+/// item = key 5 2              # type, column, line; or put its symbol in the grid
+/// ---
+/// +--+--+
+/// |s h|
+/// ```
+pub fn parse_level(text: &str, default_name: &str) -> Result<Level, Vec<MazeProblem>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let Some(split) = lines.iter().position(|line| line.trim() == "---") else {
+        let (grid, items) = take_items(text);
+        let maze = parse_maze(&grid)?;
+        if let Some(problem) = check_keys(&maze, &items, 0) {
+            return Err(vec![problem]);
+        }
+        return Ok(Level { enemies: vec![], items, ..Level::from_maze(default_name, maze) });
+    };
+
+    let mut problems = Vec::new();
+    let header_problem = |line: usize, message: String| MazeProblem {
+        position: Some((line + 1, 1)),
+        kind: ProblemKind::Header(message),
+    };
+
+    // grid problems are reported against the whole file
+    let (grid_text, mut items) = take_items(&lines[split + 1..].join("\n"));
+    let maze = parse_maze(&grid_text).map_err(|grid_problems| {
+        grid_problems
+            .into_iter()
+            .map(|mut problem| {
+                if let Some((line, _)) = &mut problem.position {
+                    *line += split + 1;
+                }
+                problem
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut name = default_name.to_string();
+    let mut start_angle = PI / 4.0;
+    let mut fog = DEFAULT_FOG;
+    let mut floor = DEFAULT_FLOOR;
+    let mut ceiling = DEFAULT_CEILING;
+    let mut music = None;
+    let mut time_limit = None;
+    let mut enemies = Vec::new();
+    let mut header_items = Vec::new();
+
+    for (n, raw) in lines[..split].iter().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            problems.push(header_problem(n, format!("expected `key = value`, found `{}`", line)));
+            continue;
+        };
+        let value = value.trim();
+        let words: Vec<&str> = value.split_whitespace().collect();
+        let number = |s: &str| s.parse::<f32>().ok().filter(|v| v.is_finite());
+
+        match key.trim() {
+            "name" => name = value.to_string(),
+            "start_angle" => match number(value) {
+                Some(degrees) => start_angle = degrees.to_radians(),
+                None => problems.push(header_problem(n, format!("start_angle `{}` is not a number", value))),
+            },
+            "fog" => match words.as_slice() {
+                ["off"] => fog = Fog::OFF,
+                ["linear", start, end] => match (number(start), number(end)) {
+                    (Some(start), Some(end)) => fog = Fog::linear(FOG_COLOR, start, end),
+                    _ => problems.push(header_problem(n, "fog linear needs <start> <end> numbers".to_string())),
+                },
+                ["exp", start, density] => match (number(start), number(density)) {
+                    (Some(start), Some(density)) => fog = Fog::exponential(FOG_COLOR, start, density),
+                    _ => problems.push(header_problem(n, "fog exp needs <start> <density> numbers".to_string())),
+                },
+                _ => problems.push(header_problem(n, format!("unknown fog `{}`, use off, linear or exp", value))),
+            },
+            "floor" | "ceiling" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(id), None) if key.trim() == "floor" => floor = id,
+                    (Some(id), None) => ceiling = id,
+                    _ => problems.push(header_problem(n, format!("{} `{}` is not a single texture id", key.trim(), value))),
+                }
+            }
+            "music" => music = Some(value.to_string()),
+            "time_limit" => match number(value) {
+                Some(seconds) if seconds > 0.0 => time_limit = Some(seconds),
+                _ => problems.push(header_problem(n, format!("time_limit `{}` is not a positive number", value))),
+            },
+            "enemy" => match words.as_slice() {
+                [kind, column, row] => {
+                    if enemies::enemy_type(kind).is_none() {
+                        problems.push(header_problem(n, format!("unknown enemy type `{}`", kind)));
+                        continue;
+                    }
+                    match (column.parse::<usize>(), row.parse::<usize>()) {
+                        (Ok(column), Ok(row)) if column > 0 && row > 0 => enemies.push((n, EnemySpawn {
+                            kind: kind.to_string(),
+                            cell: (column - 1, row - 1),
+                        })),
+                        _ => problems.push(header_problem(n, "enemy position must be <column> <line>, counted from 1".to_string())),
+                    }
+                }
+                _ => problems.push(header_problem(n, "expected `enemy = <type> <column> <line>`".to_string())),
+            },
+            "item" => match words.as_slice() {
+                [kind, column, row] => {
+                    let Some(kind) = items::item_type(kind) else {
+                        problems.push(header_problem(n, format!("unknown item type `{}`", kind)));
+                        continue;
+                    };
+                    match (column.parse::<usize>(), row.parse::<usize>()) {
+                        (Ok(column), Ok(row)) if column > 0 && row > 0 => {
+                            header_items.push((n, ItemSpawn { kind, cell: (column - 1, row - 1) }))
+                        }
+                        _ => problems.push(header_problem(n, "item position must be <column> <line>, counted from 1".to_string())),
+                    }
+                }
+                _ => problems.push(header_problem(n, "expected `item = <type> <column> <line>`".to_string())),
+            },
+            other => problems.push(header_problem(n, format!("unknown key `{}`", other))),
+        }
+    }
+
+    let mut maze = match maze {
+        Ok(maze) => maze,
+        Err(grid_problems) => {
+            problems.extend(grid_problems);
+            return Err(problems);
+        }
+    };
+
+    // spawns have to be somewhere the enemy can stand and reach the player from
+    let dist = maze.find(Cell::Start).map(|start| {
+        path::distances_from(&maze, start, |(x, y)| maze.get(x, y).is_some_and(Cell::is_passable))
+    });
+    for (n, spawn) in &enemies {
+        let (i, j) = spawn.cell;
+        let walkable = maze.get(i, j).is_some_and(Cell::is_enemy_walkable);
+        let reachable = dist.as_ref().is_some_and(|dist| dist[j][i].is_some());
+        if !walkable {
+            problems.push(header_problem(*n, format!("enemy at column {}, line {} is not on a walkable cell", i + 1, j + 1)));
+        } else if !reachable {
+            problems.push(header_problem(*n, format!("enemy at column {}, line {} can't reach the player", i + 1, j + 1)));
+        }
+    }
+
+    for (n, spawn) in header_items {
+        let (i, j) = spawn.cell;
+        if maze.get(i, j).is_some_and(Cell::is_walkable) {
+            items.push(spawn);
+        } else {
+            problems.push(header_problem(n, format!("item at column {}, line {} is not on a walkable cell", i + 1, j + 1)));
+        }
+    }
+
+    problems.extend(check_keys(&maze, &items, split + 1));
+    maze.set_plane_textures(floor, ceiling);
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok(Level {
+        name,
+        maze,
+        enemies: enemies.into_iter().map(|(_, spawn)| spawn).collect(),
+        items,
+        start_angle,
+        fog,
+        music,
+        time_limit,
+    })
+}
+
+/// Reads and parses a level file, named after the file if the header has no name.
+pub fn load_level(filename: &str) -> Result<Level, MazeError> {
+    let text = fs::read_to_string(filename).map_err(|source| MazeError::Io {
+        path: filename.to_string(),
+        source,
+    })?;
+    parse_level(&text, filename).map_err(|problems| MazeError::Invalid {
+        path: filename.to_string(),
+        problems,
+    })
+}
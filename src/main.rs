@@ -2,32 +2,43 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
 
+mod ai;
+mod animation;
+mod enemies;
+mod items;
 mod line;
 mod framebuffer;
+mod level;
 mod maze;
+mod packs;
+mod resolution;
+mod rng;
+mod sim;
 mod player;
 mod caster;
 mod sprite;
 mod textures;
 mod gui;
 mod game;
+mod headless;
 
 use raylib::prelude::*;
-use std::{f32::consts::PI, thread};
-use std::time::Duration;
-use framebuffer::Framebuffer;
+use std::f32::consts::PI;
+use std::collections::HashMap;
+use framebuffer::{Fog, Framebuffer};
 use line::line;
-use maze::{Maze,load_maze};
+use maze::{Cell, Maze};
 use player::{Player};
 use caster::{cast_ray};
 use sprite::{};
 
-use crate::game::{AppState, StateHandler, find_start_cell};
+use crate::game::{AppState, StateHandler};
 use crate::gui::screens::Screens;
-use crate::sprite::draw_sprite;
-use crate::textures::TextureManager;
+use crate::sprite::{Sprite, SpriteCutoffs, draw_sprites};
+use crate::textures::{TextureManager, get_pixel_color};
 
 use crate::sprite::Enemy; // or wherever your Enemy is
+use crate::items::Item;
 
 pub fn render_minimap(
     framebuffer: &mut Framebuffer,
@@ -35,16 +46,18 @@ pub fn render_minimap(
     block_size: usize,
     player: &Player,
     enemies: &[Enemy],
+    items: &[Item],
 ) {
     let fb_w = framebuffer.width as i32;
     let fb_h = framebuffer.height as i32;
 
-    let rows = maze.len();
+    let rows = maze.height();
     if rows == 0 { return; }
-    let cols = maze[0].len();
+    let cols = maze.width();
 
-    // Size in pixels per map cell on the minimap
-    let cell_size: i32 = 4; // tweak this if your map is big/small
+    // Size in pixels per map cell on the minimap: 4 at 600 pixels high,
+    // following the frame size but never wider than a third of it
+    let cell_size: i32 = (fb_h / 150).min(fb_w / 3 / cols.max(1) as i32).max(1);
 
     let map_pixel_w = (cols as i32) * cell_size;
     let map_pixel_h = (rows as i32) * cell_size;
@@ -54,21 +67,13 @@ pub fn render_minimap(
     let origin_y = margin;
 
     // Draw map cells
-    for (row_index, row) in maze.iter().enumerate() {
+    for (row_index, row) in maze.rows().enumerate() {
         for (col_index, &cell) in row.iter().enumerate() {
             let x0 = origin_x + (col_index as i32) * cell_size;
             let y0 = origin_y + (row_index as i32) * cell_size;
 
-            // choose color based on cell type
-            let color = match cell {
-                ' ' => continue, // skip empty space
-                '-' => Color::DARKGRAY, // wall type 1
-                '|' => Color::DARKGRAY, // wall type 1
-                '+' => Color::BROWN,    // wall type 2
-                's' | 'S' => Color::GREEN,  // start
-                'g' | 'G' => Color::YELLOW, // goal
-                _   => Color::DARKBLUE,     // other walls
-            };
+            // color comes from the tile registry, empty space is skipped
+            let Some(color) = cell.minimap_color() else { continue };
 
             framebuffer.set_current_color(color);
 
@@ -107,21 +112,62 @@ pub fn render_minimap(
         }
     };
 
+    for item in items {
+        draw_marker(item.pos.x, item.pos.y, item.kind.minimap_color);
+    }
+
     // Draw player marker (cyan)
     draw_marker(player.pos.x, player.pos.y, Color::SKYBLUE);
 
-    // Draw enemies (red)
+    // Draw enemies, colored by type
     for enemy in enemies {
-        draw_marker(enemy.pos.x, enemy.pos.y, Color::ORANGE);
+        draw_marker(enemy.pos.x, enemy.pos.y, enemy.kind.minimap_color);
     }
 }
 
-pub fn render_world(framebuffer: &mut Framebuffer, player: &Player, maze: &Maze, block_size: usize) {
+/// Brightness of east/west facing walls, so corners stay readable
+const SIDE_SHADE: f32 = 0.7;
+/// Wall height in blocks. Projected through `distance_to_projection_plane`
+/// only, so walls keep their size on screen at any render resolution.
+const WALL_HEIGHT: f32 = 0.45;
+
+fn shade(color: Color, factor: f32) -> Color {
+    Color::new(
+        (color.r as f32 * factor) as u8,
+        (color.g as f32 * factor) as u8,
+        (color.b as f32 * factor) as u8,
+        color.a,
+    )
+}
+
+/// Draws walls, floor and ceiling. Returns the wall distance of every screen
+/// column, for clipping sprites.
+pub fn render_world(framebuffer: &mut Framebuffer, player: &Player, maze: &Maze, block_size: usize, texture_manager: &TextureManager) -> Vec<f32> {
     let num_rays = framebuffer.width;
+    let mut column_depths = vec![f32::INFINITY; num_rays as usize];
     let hw = framebuffer.width as f32 /2.0;
     let hh = framebuffer.height as f32 /2.0;
     framebuffer.set_current_color(Color::WHITE);
 
+    // floor and ceiling image of each cell, looked up once per frame rather than
+    // per pixel. A plane no cell has a texture for is filled with the background
+    // color in one go instead.
+    let plane_images = |texture_key: fn(&Maze, usize, usize) -> char| -> Option<Vec<Vec<Option<&Image>>>> {
+        let images: Vec<Vec<_>> = (0..maze.height())
+            .map(|j| (0..maze.width()).map(|i| texture_manager.images.get(&texture_key(maze, i, j))).collect())
+            .collect();
+        images.iter().flatten().any(Option::is_some).then_some(images)
+    };
+    let floor_images = plane_images(Maze::floor_texture);
+    let ceiling_images = plane_images(Maze::ceiling_texture);
+    let background = framebuffer.background_color();
+    if ceiling_images.is_none() {
+        framebuffer.fill_rows(0, hh as u32, background);
+    }
+    if floor_images.is_none() {
+        framebuffer.fill_rows(hh as u32, framebuffer.height, background);
+    }
+
     //let num_rays = 5;
     for i in 0..num_rays {
         let current_ray = i as f32 / num_rays as f32;
@@ -130,19 +176,111 @@ pub fn render_world(framebuffer: &mut Framebuffer, player: &Player, maze: &Maze,
 
         let mut distance_to_wall = intersect.distance;
         distance_to_wall *= (player.a - a).cos();
+        column_depths[i as usize] = distance_to_wall;
         let distance_to_projection_plane = hw / (player.fov / 2.0).tan();
-        let stake_height = (hh / distance_to_wall) * distance_to_projection_plane * 0.15;
+        let stake_height = block_size as f32 * WALL_HEIGHT / distance_to_wall * distance_to_projection_plane;
 
         let stake_top = (hh - (stake_height / 2.0)) as u32;
-        let stake_bottom = (hh + (stake_height / 2.0)) as u32;
+        let stake_bottom = ((hh + (stake_height / 2.0)) as u32).min(framebuffer.height);
+        let side_shade = if intersect.side.is_vertical() { SIDE_SHADE } else { 1.0 };
+
+        let wall_texture = intersect.impact.texture_id();
+        match wall_texture.and_then(|key| texture_manager.images.get(&key).map(|image| (key, image))) {
+            Some((key, image)) => {
+                let tex_w = image.width as u32;
+                let tex_h = image.height as f32;
+                // Intersect::tx is on a 128 pixel scale, map it onto this texture
+                let tex_x = intersect.tx as u32 * tex_w / 128;
+                // step through the texture once per screen row, starting where the
+                // (possibly off-screen) top of the stake would be
+                let step = tex_h / stake_height;
+                let tex_top = (stake_top as f32 - (hh - stake_height / 2.0)) * step;
+
+                framebuffer.draw_span(i, stake_top, stake_bottom, distance_to_wall, |y| {
+                    let tex_y = tex_top + (y - stake_top) as f32 * step;
+                    shade(texture_manager.get_pixel_color(key, tex_x, tex_y as u32), side_shade)
+                });
+            }
+            None => {
+                let color = shade(intersect.impact.props().color, side_shade);
+                framebuffer.fill_span(i, stake_top, stake_bottom, distance_to_wall, color);
+            }
+        }
 
-        for y in stake_top..stake_bottom {
-            framebuffer.set_current_color(match intersect.impact {
-                '+' => Color::ORANGERED,
-                'g' => Color::GREEN,
-                _ => Color::YELLOW,
-            });
-            framebuffer.set_pixel(i, y, distance_to_wall);
+        // floor below the stake, ceiling mirrored above it. Each pixel looks up the
+        // world point at that row's distance along the ray and samples the image
+        // of that cell; cells without one keep the background.
+        let plane_scale = block_size as f32 * WALL_HEIGHT * distance_to_projection_plane / 2.0;
+        let ray_cos = (a - player.a).cos();
+        let (ray_dx, ray_dy) = (a.cos() / ray_cos, a.sin() / ray_cos);
+        let bs = block_size as f32;
+
+        let plane_pixel = |framebuffer: &mut Framebuffer, y: u32, row_distance: f32, images: &[Vec<Option<&Image>>]| {
+            let wx = (player.pos.x + ray_dx * row_distance) / bs;
+            let wy = (player.pos.y + ray_dy * row_distance) / bs;
+            if wx < 0.0 || wy < 0.0 {
+                return;
+            }
+            let Some(&Some(image)) = images.get(wy as usize).and_then(|row| row.get(wx as usize)) else {
+                return;
+            };
+            let tx = ((wx.fract() * image.width as f32) as i32).min(image.width - 1);
+            let ty = ((wy.fract() * image.height as f32) as i32).min(image.height - 1);
+            framebuffer.set_current_color(get_pixel_color(image, tx, ty));
+            framebuffer.set_pixel(i, y, row_distance);
+        };
+
+        if let Some(images) = &floor_images {
+            for y in stake_bottom..framebuffer.height {
+                if y as f32 > hh {
+                    plane_pixel(framebuffer, y, plane_scale / (y as f32 - hh), images);
+                }
+            }
+        }
+        if let Some(images) = &ceiling_images {
+            for y in 0..stake_top.min(framebuffer.height) {
+                if (y as f32) < hh {
+                    plane_pixel(framebuffer, y, plane_scale / (hh - y as f32), images);
+                }
+            }
+        }
+    }
+    column_depths
+}
+
+/// Draws the walls, sprites and minimap of one frame into the framebuffer.
+/// Needs no window, so it also makes the headless previews.
+pub fn render_scene(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    maze: &Maze,
+    enemies: &[Enemy],
+    items: &[Item],
+    block_size: usize,
+    texture_manager: &TextureManager,
+) {
+    let column_depths = render_world(framebuffer, player, maze, block_size, texture_manager);
+
+    let cutoffs = SpriteCutoffs::new(framebuffer, player, maze, block_size as f32);
+    let mut sprites: Vec<Sprite> = items
+        .iter()
+        .map(Item::sprite)
+        .chain(enemies.iter().map(|enemy| enemy.sprite(player.pos)))
+        .collect();
+    draw_sprites(framebuffer, player, &mut sprites, &column_depths, &cutoffs, block_size as f32, texture_manager);
+    render_minimap(framebuffer, maze, block_size, player, enemies, items);
+}
+
+/// Starts the track at `path`, or logs why it can't and returns `None`.
+fn load_music<'a>(audio: &'a RaylibAudio, path: &str) -> Option<Music<'a>> {
+    match audio.new_music(path) {
+        Ok(music) => {
+            music.play_stream();
+            Some(music)
+        }
+        Err(err) => {
+            eprintln!("{}: {}, playing without music", path, err);
+            None
         }
     }
 }
@@ -152,48 +290,127 @@ fn main() {
     let window_height = 600;
     let block_size = 100;
 
+    // `maze --render ...` writes level previews and exits without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--render") {
+        if let Err(err) = headless::run(&args[1..], block_size) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let (mut window, raylib_thread) = raylib::init()
         .size(window_width, window_height)
         .title("Raycaster Example")
+        .resizable()
         .log_level(TraceLogLevel::LOG_WARNING)
         .build();
     window.set_exit_key(None);
-
-    let audio = RaylibAudio::init_audio_device().expect("Failed to load audio device");
-    let music = audio.new_music("assets/video0.MP3").expect("failed to load music");
-    music.play_stream();
-    let damage_sound = audio.new_sound("assets/hit1.ogg").expect("Failed to load damage sound");
+    // below this the menus stop fitting
+    window.set_window_min_size(640, 400);
+    // game speed doesn't depend on this, the world runs in fixed steps
+    window.set_target_fps(120);
+
+    // without an audio device or a file the game still runs, just quieter
+    let audio = match RaylibAudio::init_audio_device() {
+        Ok(audio) => Some(audio),
+        Err(err) => {
+            eprintln!("audio device: {}, playing without sound", err);
+            None
+        }
+    };
+    let mut music_track = level::DEFAULT_MUSIC.to_string();
+    let mut music = audio.as_ref().and_then(|audio| load_music(audio, &music_track));
+    // one hit sound per file named in the enemy registry
+    let mut hit_sounds = HashMap::new();
+    for kind in enemies::ENEMY_TYPES {
+        if let Some(audio) = &audio && !hit_sounds.contains_key(kind.sound) {
+            match audio.new_sound(kind.sound) {
+                Ok(sound) => {
+                    hit_sounds.insert(kind.sound, sound);
+                }
+                Err(err) => eprintln!("{}: {}, playing without it", kind.sound, err),
+            }
+        }
+    }
     let mut framebuffer = Framebuffer::new(window_width as u32, window_height as u32, Color::BLACK);
 
-    framebuffer.set_background_color(Color::new(50, 50, 100, 255));
+    framebuffer.set_background_color(framebuffer::BACKGROUND_COLOR);
 
-    let texture_manager = TextureManager::new(&mut window, &raylib_thread);
+    let texture_manager = match TextureManager::load(&mut window, &raylib_thread, textures::TEXTURE_MANIFEST) {
+        Ok(texture_manager) => {
+            for err in &texture_manager.missing {
+                eprintln!("{}, using a placeholder", err);
+            }
+            texture_manager
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            TextureManager::empty()
+        }
+    };
     let mut game_state = AppState::init(window_width, window_height, block_size as f32, texture_manager);
 
+    let mut upload_error: Option<String> = None;
+    // size to go back to when leaving fullscreen
+    let mut windowed_size = (window_width, window_height);
+
     while !window.window_should_close() && !game_state.close_window {
+        if window.is_key_pressed(KeyboardKey::KEY_F11) {
+            if window.is_window_fullscreen() {
+                window.toggle_fullscreen();
+                window.set_window_size(windowed_size.0, windowed_size.1);
+            } else {
+                // fullscreen at the monitor's own resolution, not the window's
+                windowed_size = (window.get_screen_width(), window.get_screen_height());
+                let monitor = get_current_monitor();
+                window.set_window_size(get_monitor_width(monitor), get_monitor_height(monitor));
+                window.toggle_fullscreen();
+            }
+        }
+        // does nothing unless the window changed size
+        game_state.resize(window.get_screen_width(), window.get_screen_height());
         game_state.handle_input(&mut window);
         if game_state.close_window {break;}
-        music.update_stream();
-        if game_state.hit_frame {
-            damage_sound.play();
-            game_state.hit_frame = false;
+        // levels can bring their own track
+        if game_state.current_music() != music_track {
+            music_track = game_state.current_music().to_string();
+            music = audio.as_ref().and_then(|audio| load_music(audio, &music_track));
+        }
+        if let Some(music) = &music {
+            music.update_stream();
+        }
+        if let Some(sound) = game_state.hit_sound.take().and_then(|path| hit_sounds.get(path)) {
+            sound.play();
         }
         match game_state.current_screen {
             Screens::Game(_) => {
-                // 1. clear framebuffer
+                // 1. size the framebuffer for the render scale, then clear it
+                let (render_w, render_h) = game_state.render.size(window.get_screen_width(), window.get_screen_height());
+                framebuffer.resize(render_w, render_h);
+                framebuffer.filter = game_state.render.filter;
                 framebuffer.clear();
+                framebuffer.fog = game_state.current_fog();
                 
                 // 2. draw the maze, passing the maze and block size
                 //process_events(&mut window, &mut player, &maze, block_size as f32);
                 //render_maze(&mut framebuffer, &maze, block_size, &player);
-                render_world(&mut framebuffer, &game_state.player, &game_state.mazes[game_state.current_level], block_size);
-                
-                for enemy in &game_state.enemies {
-                    draw_sprite(&mut framebuffer, &game_state.player, enemy, &game_state.texture_manager);
+                // draw where things are between the last two world steps
+                let world = game_state.world();
+                let (player, enemies) = world.interpolated(game_state.stepper.alpha());
+                render_scene(&mut framebuffer, &player, &world.maze, &enemies, &world.items, block_size, &game_state.texture_manager);
+                // 3. swap buffers, reporting each new upload error once
+                match framebuffer.swap_buffers(&mut window, &raylib_thread, &game_state) {
+                    Ok(()) => upload_error = None,
+                    Err(err) => {
+                        if upload_error.as_ref() != Some(&err) {
+                            eprintln!("could not upload the frame: {}", err);
+                        }
+                        upload_error = Some(err);
+                    }
                 }
-                render_minimap(&mut framebuffer, &game_state.mazes[game_state.current_level], game_state.block_size as usize, &game_state.player, &game_state.enemies);
-                // 3. swap buffers
-                framebuffer.swap_buffers(&mut window, &raylib_thread, &game_state);
+                game_state.render.observe_frame(window.get_frame_time());
             }
             Screens::MainMenu(_) => {
                 let mut d = window.begin_drawing(&raylib_thread);
@@ -205,7 +422,5 @@ fn main() {
                 game_state.current_screen.render(&mut d);
             }
         }
-
-        thread::sleep(Duration::from_millis(8));
     }
 }
\ No newline at end of file
@@ -1,13 +0,0 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub type  Maze = Vec<Vec<char>>;
-pub fn load_maze(filename: &str) -> Vec<Vec<char>> {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| line.unwrap().chars().collect())
-        .collect()
-}
\ No newline at end of file
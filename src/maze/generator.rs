@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+
+use crate::maze::{Maze, MazeError, parse_maze};
+use crate::rng::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Depth-first carving: long winding corridors, few junctions
+    Backtracker,
+    /// Randomized Prim: grows from one cell, lots of short dead ends
+    Prim,
+    /// Randomized Kruskal: joins random cells, fairly uniform texture
+    Kruskal,
+    /// Backtracker with its dead ends knocked through, so the maze has loops
+    Braided,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Algorithm::Backtracker, Algorithm::Prim, Algorithm::Kruskal, Algorithm::Braided];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Backtracker => "Backtracker",
+            Algorithm::Prim => "Prim",
+            Algorithm::Kruskal => "Kruskal",
+            Algorithm::Braided => "Braided",
+        }
+    }
+
+    /// The one after this in `ALL`, wrapping around; used by the menu button.
+    pub fn next(self) -> Self {
+        let i = Algorithm::ALL.iter().position(|&a| a == self).unwrap_or(0);
+        Algorithm::ALL[(i + 1) % Algorithm::ALL.len()]
+    }
+}
+
+/// Smallest and largest width or height `generate` accepts, in maze cells.
+pub const MIN_SIZE: usize = 2;
+pub const MAX_SIZE: usize = 40;
+
+/// Size is in maze cells, not characters: each cell is drawn 3 characters wide
+/// and 2 tall, like the hand-drawn levels.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    pub algorithm: Algorithm,
+}
+
+impl GeneratorConfig {
+    /// Level name shown in the game, enough to make the same maze again.
+    pub fn name(&self) -> String {
+        format!("Random {} {}x{} #{}", self.algorithm.name(), self.width, self.height, self.seed)
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig { width: 8, height: 6, seed: 0, algorithm: Algorithm::Backtracker }
+    }
+}
+
+/// Passages between cells: `east[y][x]` opens (x, y) to (x + 1, y),
+/// `south[y][x]` opens (x, y) to (x, y + 1).
+struct Grid {
+    width: usize,
+    height: usize,
+    east: Vec<Vec<bool>>,
+    south: Vec<Vec<bool>>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            east: vec![vec![false; width]; height],
+            south: vec![vec![false; width]; height],
+        }
+    }
+
+    fn neighbors(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut out = Vec::with_capacity(4);
+        if x > 0 { out.push((x - 1, y)); }
+        if x + 1 < self.width { out.push((x + 1, y)); }
+        if y > 0 { out.push((x, y - 1)); }
+        if y + 1 < self.height { out.push((x, y + 1)); }
+        out
+    }
+
+    fn is_open(&self, (ax, ay): (usize, usize), (bx, by): (usize, usize)) -> bool {
+        if ay == by {
+            self.east[ay][ax.min(bx)]
+        } else {
+            self.south[ay.min(by)][ax]
+        }
+    }
+
+    fn open(&mut self, (ax, ay): (usize, usize), (bx, by): (usize, usize)) {
+        if ay == by {
+            self.east[ay][ax.min(bx)] = true;
+        } else {
+            self.south[ay.min(by)][ax] = true;
+        }
+    }
+
+    fn exits(&self, cell: (usize, usize)) -> Vec<(usize, usize)> {
+        self.neighbors(cell).into_iter().filter(|&n| self.is_open(cell, n)).collect()
+    }
+
+    /// Cell farthest from `from` along the passages, with its distance.
+    fn farthest(&self, from: (usize, usize)) -> ((usize, usize), usize) {
+        let mut dist = vec![vec![usize::MAX; self.width]; self.height];
+        dist[from.1][from.0] = 0;
+        let mut queue = VecDeque::from([from]);
+        let mut best = (from, 0);
+        while let Some(cell) = queue.pop_front() {
+            let d = dist[cell.1][cell.0];
+            if d > best.1 {
+                best = (cell, d);
+            }
+            for next in self.exits(cell) {
+                if dist[next.1][next.0] == usize::MAX {
+                    dist[next.1][next.0] = d + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        best
+    }
+}
+
+fn backtracker(grid: &mut Grid, rng: &mut Rng) {
+    let mut visited = vec![vec![false; grid.width]; grid.height];
+    let start = (rng.below(grid.width), rng.below(grid.height));
+    visited[start.1][start.0] = true;
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let fresh: Vec<_> = grid.neighbors(cell).into_iter().filter(|&(x, y)| !visited[y][x]).collect();
+        if fresh.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let next = fresh[rng.below(fresh.len())];
+        grid.open(cell, next);
+        visited[next.1][next.0] = true;
+        stack.push(next);
+    }
+}
+
+fn prim(grid: &mut Grid, rng: &mut Rng) {
+    let mut visited = vec![vec![false; grid.width]; grid.height];
+    let start = (rng.below(grid.width), rng.below(grid.height));
+    visited[start.1][start.0] = true;
+    // walls between a visited cell and its neighbour
+    let mut frontier: Vec<_> = grid.neighbors(start).into_iter().map(|n| (start, n)).collect();
+
+    while !frontier.is_empty() {
+        let (from, to) = frontier.swap_remove(rng.below(frontier.len()));
+        if visited[to.1][to.0] {
+            continue;
+        }
+        grid.open(from, to);
+        visited[to.1][to.0] = true;
+        frontier.extend(grid.neighbors(to).into_iter().filter(|&(x, y)| !visited[y][x]).map(|n| (to, n)));
+    }
+}
+
+fn kruskal(grid: &mut Grid, rng: &mut Rng) {
+    let width = grid.width;
+    let index = |(x, y): (usize, usize)| y * width + x;
+    let mut parent: Vec<usize> = (0..grid.width * grid.height).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut walls = Vec::new();
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if x + 1 < grid.width { walls.push(((x, y), (x + 1, y))); }
+            if y + 1 < grid.height { walls.push(((x, y), (x, y + 1))); }
+        }
+    }
+    rng.shuffle(&mut walls);
+
+    for (a, b) in walls {
+        let (ra, rb) = (root(&mut parent, index(a)), root(&mut parent, index(b)));
+        if ra != rb {
+            parent[ra] = rb;
+            grid.open(a, b);
+        }
+    }
+}
+
+/// Opens one extra wall from every dead end, preferring another dead end so a
+/// single cut removes two of them.
+fn braid(grid: &mut Grid, rng: &mut Rng) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let cell = (x, y);
+            if grid.exits(cell).len() != 1 {
+                continue;
+            }
+            let closed: Vec<_> = grid.neighbors(cell).into_iter().filter(|&n| !grid.is_open(cell, n)).collect();
+            let dead: Vec<_> = closed.iter().copied().filter(|&n| grid.exits(n).len() == 1).collect();
+            let pool = if dead.is_empty() { &closed } else { &dead };
+            if !pool.is_empty() {
+                let next = pool[rng.below(pool.len())];
+                grid.open(cell, next);
+            }
+        }
+    }
+}
+
+/// Builds the maze as text in the same `+--+` / `|` style as the level files.
+pub fn generate_text(config: &GeneratorConfig) -> String {
+    let mut grid = Grid::new(config.width.max(2), config.height.max(2));
+    let mut rng = Rng::new(config.seed);
+
+    match config.algorithm {
+        Algorithm::Backtracker => backtracker(&mut grid, &mut rng),
+        Algorithm::Prim => prim(&mut grid, &mut rng),
+        Algorithm::Kruskal => kruskal(&mut grid, &mut rng),
+        Algorithm::Braided => {
+            backtracker(&mut grid, &mut rng);
+            braid(&mut grid, &mut rng);
+        }
+    }
+
+    // two BFS passes find the ends of a longest path, put s and g there
+    let (start, _) = grid.farthest((0, 0));
+    let (goal, _) = grid.farthest(start);
+
+    let mut text = String::new();
+    for y in 0..grid.height {
+        text.push('+');
+        for x in 0..grid.width {
+            let open = y > 0 && grid.south[y - 1][x];
+            text.push_str(if open { "  +" } else { "--+" });
+        }
+        text.push('\n');
+
+        text.push('|');
+        for x in 0..grid.width {
+            text.push_str(if (x, y) == start {
+                "s "
+            } else if (x, y) == goal {
+                " g"
+            } else {
+                "  "
+            });
+            let open = x + 1 < grid.width && grid.east[y][x];
+            text.push(if open { ' ' } else { '|' });
+        }
+        text.push('\n');
+    }
+    text.push('+');
+    for _ in 0..grid.width {
+        text.push_str("--+");
+    }
+    text.push('\n');
+    text
+}
+
+/// Generates a playable maze; the same config always gives the same maze. Fails
+/// for a size outside `MIN_SIZE..=MAX_SIZE`, or if the text doesn't pass `parse_maze`.
+pub fn generate(config: &GeneratorConfig) -> Result<Maze, MazeError> {
+    let sizes = MIN_SIZE..=MAX_SIZE;
+    if !sizes.contains(&config.width) || !sizes.contains(&config.height) {
+        return Err(MazeError::Generate {
+            name: config.name(),
+            message: format!("size must be {}x{} to {}x{} cells", MIN_SIZE, MIN_SIZE, MAX_SIZE, MAX_SIZE),
+        });
+    }
+    parse_maze(&generate_text(config)).map_err(|problems| MazeError::Invalid { path: config.name(), problems })
+}
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use raylib::prelude::Color;
+
+pub mod generator;
+pub mod path;
+
+/// One tile of the maze grid. Every subsystem asks the cell for its
+/// properties instead of interpreting the raw file characters itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Empty,
+    Start,
+    Goal,
+    /// `+`
+    Corner,
+    /// `-`
+    HorizontalWall,
+    /// `|`
+    VerticalWall,
+    /// `D`, opened with the interact key
+    Door,
+    /// `L`, `R` or `U`, opens like a door but uses up a key of its color
+    LockedDoor(KeyColor),
+    /// `O`, a door that has finished opening
+    OpenDoor,
+    /// Any other character, drawn as a generic wall
+    Wall(char),
+}
+
+/// Pairs locked doors with the keys that open them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyColor {
+    Gold,
+    Red,
+    Blue,
+}
+
+impl KeyColor {
+    pub const ALL: [KeyColor; 3] = [KeyColor::Gold, KeyColor::Red, KeyColor::Blue];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyColor::Gold => "gold",
+            KeyColor::Red => "red",
+            KeyColor::Blue => "blue",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            KeyColor::Gold => Color::GOLD,
+            KeyColor::Red => Color::RED,
+            KeyColor::Blue => Color::BLUE,
+        }
+    }
+}
+
+/// Static properties of a tile kind.
+#[derive(Debug, Clone, Copy)]
+pub struct TileProps {
+    pub player_walkable: bool,
+    pub enemy_walkable: bool,
+    /// Stops rays, so it is drawn as a wall
+    pub opaque: bool,
+    /// Key into the TextureManager for its wall (or floor) texture
+    pub texture: Option<char>,
+    /// Flat color used when there is no texture
+    pub color: Color,
+    /// None means the minimap leaves the cell empty
+    pub minimap_color: Option<Color>,
+}
+
+impl Cell {
+    pub fn from_char(c: char) -> Self {
+        match c {
+            ' ' => Cell::Empty,
+            's' | 'S' => Cell::Start,
+            'g' | 'G' => Cell::Goal,
+            '+' => Cell::Corner,
+            '-' => Cell::HorizontalWall,
+            '|' => Cell::VerticalWall,
+            'D' => Cell::Door,
+            'L' => Cell::LockedDoor(KeyColor::Gold),
+            'R' => Cell::LockedDoor(KeyColor::Red),
+            'U' => Cell::LockedDoor(KeyColor::Blue),
+            'O' => Cell::OpenDoor,
+            other => Cell::Wall(other),
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Cell::Empty => ' ',
+            Cell::Start => 's',
+            Cell::Goal => 'g',
+            Cell::Corner => '+',
+            Cell::HorizontalWall => '-',
+            Cell::VerticalWall => '|',
+            Cell::Door => 'D',
+            Cell::LockedDoor(KeyColor::Gold) => 'L',
+            Cell::LockedDoor(KeyColor::Red) => 'R',
+            Cell::LockedDoor(KeyColor::Blue) => 'U',
+            Cell::OpenDoor => 'O',
+            Cell::Wall(c) => c,
+        }
+    }
+
+    /// The tile registry: one row per cell kind.
+    pub fn props(self) -> TileProps {
+        let wall = |texture: char, color: Color, minimap_color: Color| TileProps {
+            player_walkable: false,
+            enemy_walkable: false,
+            opaque: true,
+            texture: Some(texture),
+            color,
+            minimap_color: Some(minimap_color),
+        };
+        match self {
+            Cell::Empty => TileProps {
+                player_walkable: true,
+                enemy_walkable: true,
+                opaque: false,
+                texture: None,
+                color: Color::BLANK,
+                minimap_color: None,
+            },
+            // enemies keep away from the spawn cell
+            Cell::Start => TileProps {
+                player_walkable: true,
+                enemy_walkable: false,
+                opaque: false,
+                texture: None,
+                color: Color::BLANK,
+                minimap_color: Some(Color::GREEN),
+            },
+            // the goal is drawn like a wall, but walking into it wins the level
+            Cell::Goal => TileProps {
+                player_walkable: true,
+                enemy_walkable: true,
+                opaque: true,
+                texture: Some('g'),
+                color: Color::GREEN,
+                minimap_color: Some(Color::YELLOW),
+            },
+            Cell::Corner => wall('+', Color::ORANGERED, Color::BROWN),
+            Cell::HorizontalWall => wall('-', Color::YELLOW, Color::DARKGRAY),
+            Cell::VerticalWall => wall('|', Color::YELLOW, Color::DARKGRAY),
+            // doors are walls until opened, see `Maze::door_openness`
+            Cell::Door => wall('D', Color::new(140, 90, 40, 255), Color::BEIGE),
+            Cell::LockedDoor(key) => wall(self.to_char(), key.color(), key.color()),
+            Cell::OpenDoor => TileProps {
+                minimap_color: Some(Color::DARKBROWN),
+                ..Cell::Empty.props()
+            },
+            Cell::Wall(c) => wall(c, Color::YELLOW, Color::DARKBLUE),
+        }
+    }
+
+    /// Blocks the player: anything the player can't walk on.
+    pub fn is_solid(self) -> bool {
+        !self.is_walkable()
+    }
+
+    pub fn is_walkable(self) -> bool {
+        self.props().player_walkable
+    }
+
+    pub fn is_enemy_walkable(self) -> bool {
+        self.props().enemy_walkable
+    }
+
+    pub fn is_door(self) -> bool {
+        matches!(self, Cell::Door | Cell::LockedDoor(_))
+    }
+
+    /// Walkable, or a door the player can open. Keys for locked doors are not
+    /// checked here, see `path::winnable`.
+    pub fn is_passable(self) -> bool {
+        self.is_walkable() || self.is_door()
+    }
+
+    pub fn is_opaque(self) -> bool {
+        self.props().opaque
+    }
+
+    pub fn texture_id(self) -> Option<char> {
+        self.props().texture
+    }
+
+    pub fn minimap_color(self) -> Option<Color> {
+        self.props().minimap_color
+    }
+
+    /// Texture key for the floor under this cell when it has its own instead of
+    /// the level's, see `Maze::floor_texture`.
+    pub fn floor_texture(self) -> Option<char> {
+        match self {
+            Cell::Start => Some('S'), // start pad
+            _ => None,
+        }
+    }
+}
+
+/// Texture keys of the floor and ceiling of a level that doesn't pick its own.
+pub const DEFAULT_FLOOR: char = '_';
+pub const DEFAULT_CEILING: char = '^';
+
+/// The level grid, indexed as `get(column, row)`.
+#[derive(Debug, Clone)]
+pub struct Maze {
+    cells: Vec<Vec<Cell>>,
+    width: usize,
+    /// How far each sliding door is open, 0.0 to 1.0; closed doors are left out
+    door_openness: HashMap<(usize, usize), f32>,
+    /// Texture keys of the floor and ceiling, see `set_plane_textures`
+    floor: char,
+    ceiling: char,
+}
+
+impl Maze {
+    pub fn from_rows(rows: Vec<Vec<char>>) -> Self {
+        let cells: Vec<Vec<Cell>> = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Cell::from_char).collect())
+            .collect();
+        let width = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+        Maze { cells, width, door_openness: HashMap::new(), floor: DEFAULT_FLOOR, ceiling: DEFAULT_CEILING }
+    }
+
+    /// Number of columns (of the longest row).
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Option<Cell> {
+        self.cells.get(j).and_then(|row| row.get(i)).copied()
+    }
+
+    /// Cell under a world position, None when outside the grid.
+    pub fn cell_at(&self, x: f32, y: f32, block_size: f32) -> Option<Cell> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        self.get((x / block_size) as usize, (y / block_size) as usize)
+    }
+
+    /// Replaces a cell, e.g. when a door finishes opening. Outside the grid does nothing.
+    pub fn set(&mut self, i: usize, j: usize, cell: Cell) {
+        if let Some(slot) = self.cells.get_mut(j).and_then(|row| row.get_mut(i)) {
+            *slot = cell;
+        }
+    }
+
+    /// How far the door at (column, row) has slid open, 0.0 for closed doors
+    /// and for any other cell.
+    pub fn door_openness(&self, i: usize, j: usize) -> f32 {
+        self.door_openness.get(&(i, j)).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_door_openness(&mut self, i: usize, j: usize, openness: f32) {
+        self.door_openness.insert((i, j), openness.clamp(0.0, 1.0));
+    }
+
+    /// The run of door cells like (column, row) that it belongs to, down the
+    /// column if `along_rows` or else along the row, as (first index, length).
+    /// Adjacent door cells of one kind open together, so they slide as one panel.
+    pub fn door_extent(&self, i: usize, j: usize, along_rows: bool) -> (usize, usize) {
+        let Some(cell) = self.get(i, j) else { return (0, 0) };
+        let at = |k: usize| if along_rows { self.get(i, k) } else { self.get(k, j) };
+        let index = if along_rows { j } else { i };
+        let mut first = index;
+        while first > 0 && at(first - 1) == Some(cell) {
+            first -= 1;
+        }
+        let mut end = index + 1;
+        while at(end) == Some(cell) {
+            end += 1;
+        }
+        (first, end - first)
+    }
+
+    /// Which way the door at (column, row) runs, as the `along_rows` to pass to
+    /// `door_extent`: down its column if it continues into the cells above or
+    /// below, along its row if it continues sideways, and for a lone door cell
+    /// down the column only when it sits between walls above and below.
+    pub fn door_along_rows(&self, i: usize, j: usize) -> bool {
+        if self.door_extent(i, j, true).1 > 1 {
+            return true;
+        }
+        if self.door_extent(i, j, false).1 > 1 {
+            return false;
+        }
+        let solid = |i: Option<usize>, j: Option<usize>| match (i, j) {
+            (Some(i), Some(j)) => self.get(i, j).is_some_and(|cell| cell.is_solid()),
+            _ => false,
+        };
+        let vertical = solid(Some(i), j.checked_sub(1)) && solid(Some(i), Some(j + 1));
+        let horizontal = solid(i.checked_sub(1), Some(j)) && solid(Some(i + 1), Some(j));
+        vertical && !horizontal
+    }
+
+    /// Picks the floor and ceiling textures, by texture key, for the whole level.
+    pub fn set_plane_textures(&mut self, floor: char, ceiling: char) {
+        self.floor = floor;
+        self.ceiling = ceiling;
+    }
+
+    /// Texture key for the floor under (column, row): the cell's own, see
+    /// `Cell::floor_texture`, or else the level's.
+    pub fn floor_texture(&self, i: usize, j: usize) -> char {
+        self.get(i, j).and_then(Cell::floor_texture).unwrap_or(self.floor)
+    }
+
+    /// Texture key for the ceiling above (column, row).
+    pub fn ceiling_texture(&self, _i: usize, _j: usize) -> char {
+        self.ceiling
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.iter().map(|row| row.as_slice())
+    }
+
+    /// First cell of the given kind, as (column, row).
+    pub fn find(&self, cell: Cell) -> Option<(usize, usize)> {
+        self.cells.iter().enumerate().find_map(|(j, row)| {
+            row.iter().position(|&c| c == cell).map(|i| (i, j))
+        })
+    }
+}
+
+/// Something wrong with a maze file. `position` is the 1-based (line, column)
+/// it refers to, or None when it is about the whole file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MazeProblem {
+    pub position: Option<(usize, usize)>,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProblemKind {
+    Empty,
+    /// Row length differs from the first row
+    RaggedRow { expected: usize, found: usize },
+    NoStart,
+    /// A second `s`; the position is the extra one
+    ExtraStart,
+    NoGoal,
+    /// A walkable cell on the outer edge lets the player leave the map
+    OpenBorder,
+    /// No walkable route from the start to any goal; the position is the start
+    GoalUnreachable,
+    /// Every route to a goal has a locked door the keys in the level can't
+    /// open; the position is the start
+    NotEnoughKeys,
+    /// Problem in a level file header, see `level::parse_level`
+    Header(String),
+}
+
+impl fmt::Display for MazeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        match &self.kind {
+            ProblemKind::Empty => write!(f, "maze is empty"),
+            ProblemKind::RaggedRow { expected, found } => {
+                write!(f, "row has {} columns, expected {}", found, expected)
+            }
+            ProblemKind::NoStart => write!(f, "no start cell 's'"),
+            ProblemKind::ExtraStart => write!(f, "more than one start cell 's'"),
+            ProblemKind::NoGoal => write!(f, "no goal cell 'g'"),
+            ProblemKind::OpenBorder => write!(f, "border is open here"),
+            ProblemKind::GoalUnreachable => write!(f, "no goal 'g' can be reached from this start"),
+            ProblemKind::NotEnoughKeys => write!(f, "the goal is behind locked doors without enough keys of their color"),
+            ProblemKind::Header(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MazeError {
+    Io { path: String, source: io::Error },
+    Invalid { path: String, problems: Vec<MazeProblem> },
+    /// The generator couldn't make a maze from its settings, see `generator::generate`
+    Generate { name: String, message: String },
+}
+
+impl MazeError {
+    /// File (or generated level) the error came from.
+    pub fn path(&self) -> &str {
+        match self {
+            MazeError::Io { path, .. } | MazeError::Invalid { path, .. } => path,
+            MazeError::Generate { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::Io { path, source } => write!(f, "{}: {}", path, source),
+            MazeError::Generate { name, message } => write!(f, "{}: {}", name, message),
+            MazeError::Invalid { path, problems } => {
+                write!(f, "{}: {} problem(s)", path, problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MazeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MazeError::Io { source, .. } => Some(source),
+            MazeError::Invalid { .. } | MazeError::Generate { .. } => None,
+        }
+    }
+}
+
+/// Parses and validates maze text: rows must all be the same length, there must
+/// be exactly one start and at least one goal, the border must be closed and a
+/// goal must be reachable from the start.
+pub fn parse_maze(text: &str) -> Result<Maze, Vec<MazeProblem>> {
+    let mut rows: Vec<Vec<char>> = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r').chars().collect())
+        .collect();
+    // trailing blank lines are just the end of the file
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+
+    let mut problems = Vec::new();
+    let at = |j: usize, i: usize, kind| MazeProblem { position: Some((j + 1, i + 1)), kind };
+
+    if rows.is_empty() {
+        problems.push(MazeProblem { position: None, kind: ProblemKind::Empty });
+        return Err(problems);
+    }
+
+    let width = rows[0].len();
+    for (j, row) in rows.iter().enumerate() {
+        if row.len() != width {
+            let column = row.len().min(width);
+            problems.push(at(j, column, ProblemKind::RaggedRow { expected: width, found: row.len() }));
+        }
+    }
+
+    let maze = Maze::from_rows(rows);
+    let mut starts = 0;
+    let mut goals = 0;
+    for (j, row) in maze.rows().enumerate() {
+        for (i, &cell) in row.iter().enumerate() {
+            match cell {
+                Cell::Start => {
+                    starts += 1;
+                    if starts > 1 {
+                        problems.push(at(j, i, ProblemKind::ExtraStart));
+                    }
+                }
+                Cell::Goal => goals += 1,
+                _ => {}
+            }
+            let on_border = j == 0 || j + 1 == maze.height() || i == 0 || i + 1 == row.len();
+            if on_border && !cell.is_solid() {
+                problems.push(at(j, i, ProblemKind::OpenBorder));
+            }
+        }
+    }
+    if starts == 0 {
+        problems.push(MazeProblem { position: None, kind: ProblemKind::NoStart });
+    }
+    if goals == 0 {
+        problems.push(MazeProblem { position: None, kind: ProblemKind::NoGoal });
+    }
+
+    // only worth searching once the layout itself is sound
+    if problems.is_empty() && !path::analyze(&maze).goal_reachable {
+        let position = maze.find(Cell::Start).map(|(i, j)| (j + 1, i + 1));
+        problems.push(MazeProblem { position, kind: ProblemKind::GoalUnreachable });
+    }
+
+    if problems.is_empty() { Ok(maze) } else { Err(problems) }
+}
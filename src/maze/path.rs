@@ -0,0 +1,312 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::maze::{Cell, KeyColor, Maze};
+
+/// Grid coordinates as (column, row).
+pub type GridPos = (usize, usize);
+
+/// Result of checking a maze from its start cell.
+#[derive(Debug, Clone)]
+pub struct Reachability {
+    pub goal_reachable: bool,
+    /// Steps from the start to the nearest goal, if any goal can be reached
+    pub shortest_path_len: Option<usize>,
+    /// Walkable cells in branches that lead nowhere
+    pub dead_ends: Vec<GridPos>,
+}
+
+/// 4-neighbourhood of a cell that lies inside the grid.
+pub fn neighbors(maze: &Maze, (i, j): GridPos) -> impl Iterator<Item = GridPos> + '_ {
+    let candidates = [
+        (i.wrapping_add(1), j),
+        (i.wrapping_sub(1), j),
+        (i, j.wrapping_add(1)),
+        (i, j.wrapping_sub(1)),
+    ];
+    candidates.into_iter().filter(move |&(x, y)| maze.get(x, y).is_some())
+}
+
+/// Breadth-first distances (in steps) from `from` to every cell, None where unreachable.
+/// Indexed as `[row][column]`.
+pub fn distances_from(maze: &Maze, from: GridPos, passable: impl Fn(GridPos) -> bool) -> Vec<Vec<Option<usize>>> {
+    let mut dist = vec![vec![None; maze.width()]; maze.height()];
+    if maze.get(from.0, from.1).is_none() {
+        return dist;
+    }
+    dist[from.1][from.0] = Some(0);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(pos) = queue.pop_front() {
+        let d = dist[pos.1][pos.0].unwrap_or(0);
+        for next in neighbors(maze, pos) {
+            if dist[next.1][next.0].is_none() && passable(next) {
+                dist[next.1][next.0] = Some(d + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+/// Shortest path from `from` to `to`, both ends included. None if `to` can't be reached.
+pub fn shortest_path(maze: &Maze, from: GridPos, to: GridPos, passable: impl Fn(GridPos) -> bool) -> Option<Vec<GridPos>> {
+    let dist = distances_from(maze, from, &passable);
+    let mut d = dist.get(to.1)?.get(to.0).copied()??;
+
+    // walk back downhill from the target
+    let mut path = vec![to];
+    let mut pos = to;
+    while d > 0 {
+        pos = neighbors(maze, pos).find(|&(x, y)| dist[y][x] == Some(d - 1))?;
+        path.push(pos);
+        d -= 1;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// A* search from `from` to `to` with a Manhattan heuristic. Same result as
+/// `shortest_path` but only explores towards the target, so it is cheap enough
+/// to run every few steps for each enemy.
+pub fn astar(maze: &Maze, from: GridPos, to: GridPos, passable: impl Fn(GridPos) -> bool) -> Option<Vec<GridPos>> {
+    maze.get(from.0, from.1)?;
+    maze.get(to.0, to.1)?;
+    let heuristic = |(i, j): GridPos| i.abs_diff(to.0) + j.abs_diff(to.1);
+
+    let mut cost = vec![vec![usize::MAX; maze.width()]; maze.height()];
+    let mut came_from = vec![vec![None; maze.width()]; maze.height()];
+    let mut open = BinaryHeap::new();
+    cost[from.1][from.0] = 0;
+    open.push(Reverse((heuristic(from), 0, from)));
+
+    while let Some(Reverse((_, g, pos))) = open.pop() {
+        if pos == to {
+            let mut path = vec![to];
+            let mut cur = to;
+            while let Some(prev) = came_from[cur.1][cur.0] {
+                path.push(prev);
+                cur = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if g > cost[pos.1][pos.0] {
+            continue; // stale entry
+        }
+        for next in neighbors(maze, pos) {
+            let g_next = g + 1;
+            if g_next < cost[next.1][next.0] && passable(next) {
+                cost[next.1][next.0] = g_next;
+                came_from[next.1][next.0] = Some(pos);
+                open.push(Reverse((g_next + heuristic(next), g_next, next)));
+            }
+        }
+    }
+    None
+}
+
+/// True for mazes drawn in the usual `+--+` style: a `+` at every third column
+/// of every other row, all the way to the edges.
+fn is_lattice(maze: &Maze) -> bool {
+    let (w, h) = (maze.width(), maze.height());
+    w % 3 == 1
+        && h % 2 == 1
+        && (0..h).step_by(2).all(|j| (0..w).step_by(3).all(|i| maze.get(i, j) == Some(Cell::Corner)))
+}
+
+/// Cells that are filled in together by `dead_ends`. In a lattice maze (see
+/// `is_lattice`) cells are two characters wide, so the two columns between a pair of
+/// `+` count as one room; any other layout uses single cells.
+fn room_of(lattice: bool, (i, j): GridPos) -> GridPos {
+    if lattice && i % 3 == 2 { (i - 1, j) } else { (i, j) }
+}
+
+/// Dead-end filling: keeps removing rooms (see `room_of`) that connect to at most
+/// one other room until nothing changes, then returns the cells of every removed
+/// room. Rooms holding the start or a goal are never removed.
+pub fn dead_ends(maze: &Maze, passable: impl Fn(GridPos) -> bool) -> Vec<GridPos> {
+    use std::collections::{HashMap, HashSet};
+
+    let mut cells: HashMap<GridPos, Vec<GridPos>> = HashMap::new();
+    let mut links: HashMap<GridPos, HashSet<GridPos>> = HashMap::new();
+    let mut keep: HashSet<GridPos> = HashSet::new();
+    let lattice = is_lattice(maze);
+
+    for (j, row) in maze.rows().enumerate() {
+        for (i, &cell) in row.iter().enumerate() {
+            if !passable((i, j)) {
+                continue;
+            }
+            let room = room_of(lattice, (i, j));
+            cells.entry(room).or_default().push((i, j));
+            links.entry(room).or_default();
+            if matches!(cell, Cell::Start | Cell::Goal) {
+                keep.insert(room);
+            }
+            for next in neighbors(maze, (i, j)).filter(|&p| passable(p)) {
+                let other = room_of(lattice, next);
+                if other != room {
+                    links.entry(room).or_default().insert(other);
+                }
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let ends: Vec<GridPos> = links
+            .iter()
+            .filter(|(room, exits)| exits.len() <= 1 && !keep.contains(*room))
+            .map(|(room, _)| *room)
+            .collect();
+        for room in ends {
+            if let Some(exits) = links.remove(&room) {
+                for exit in exits {
+                    if let Some(other) = links.get_mut(&exit) {
+                        other.remove(&room);
+                    }
+                }
+                removed.extend(cells.remove(&room).unwrap_or_default());
+                changed = true;
+            }
+        }
+    }
+    removed.sort_by_key(|&(i, j)| (j, i));
+    removed
+}
+
+/// Whether the player can get from the start to a goal when each locked door
+/// takes a key of its color, found at `keys`. Adjacent locked cells of one color
+/// are one door and take one key.
+///
+/// Breadth-first over (cell, keys picked up and spent of each color), with both
+/// counts capped by the keys of that color in the level, so the search is bounded
+/// by the cells times those counts rather than by the ways to open doors. A door
+/// is paid for when walked through away from the start, going back is free. Keys
+/// are counted rather than told apart, so walking back onto a key can stand in
+/// for another key of its color that lies out of reach.
+pub fn winnable(maze: &Maze, keys: &[(GridPos, KeyColor)]) -> bool {
+    use std::collections::HashSet;
+
+    const COLORS: usize = KeyColor::ALL.len();
+
+    let Some(start) = maze.find(Cell::Start) else { return false };
+    let mut total = [0; COLORS];
+    for &(_, color) in keys {
+        total[color as usize] += 1;
+    }
+    // which way through a door is "away from the start"
+    let dist = distances_from(maze, start, |(x, y)| maze.get(x, y).is_some_and(Cell::is_passable));
+    let further = |from: GridPos, to: GridPos| dist[to.1][to.0] > dist[from.1][from.0];
+
+    let first = (start, [0; COLORS], [0; COLORS]);
+    let mut seen = HashSet::from([first]);
+    let mut queue = VecDeque::from([first]);
+    while let Some((pos, picked, spent)) = queue.pop_front() {
+        for next in neighbors(maze, pos) {
+            let (mut picked, mut spent) = (picked, spent);
+            match maze.get(next.0, next.1) {
+                Some(Cell::Goal) => return true,
+                Some(cell @ Cell::LockedDoor(color)) => {
+                    let c = color as usize;
+                    let same_door = maze.get(pos.0, pos.1) == Some(cell);
+                    if !same_door && further(pos, next) {
+                        if spent[c] == picked[c] {
+                            continue;
+                        }
+                        spent[c] += 1;
+                    }
+                }
+                Some(cell) if cell.is_passable() => {
+                    for &(_, color) in keys.iter().filter(|&&(at, _)| at == next) {
+                        let c = color as usize;
+                        picked[c] = (picked[c] + 1).min(total[c]);
+                    }
+                }
+                _ => continue,
+            }
+            let state = (next, picked, spent);
+            if seen.insert(state) {
+                queue.push_back(state);
+            }
+        }
+    }
+    false
+}
+
+/// Checks that the player can get from the start to a goal.
+pub fn analyze(maze: &Maze) -> Reachability {
+    let walkable = |(i, j): GridPos| maze.get(i, j).is_some_and(Cell::is_passable);
+
+    let shortest_path_len = maze.find(Cell::Start).and_then(|start| {
+        let dist = distances_from(maze, start, walkable);
+        maze.rows()
+            .enumerate()
+            .flat_map(|(j, row)| row.iter().enumerate().map(move |(i, &cell)| (i, j, cell)))
+            .filter(|&(_, _, cell)| cell == Cell::Goal)
+            .filter_map(|(i, j, _)| dist[j][i])
+            .min()
+    });
+
+    Reachability {
+        goal_reachable: shortest_path_len.is_some(),
+        shortest_path_len,
+        dead_ends: dead_ends(maze, walkable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze(rows: &[&str]) -> Maze {
+        Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect())
+    }
+
+    #[test]
+    fn locked_doors_need_a_reachable_key_of_their_color() {
+        let corridor = maze(&["+--+--+--+", "|s  L   g|", "+--+--+--+"]);
+        assert!(!winnable(&corridor, &[]));
+        assert!(winnable(&corridor, &[((2, 1), KeyColor::Gold)]));
+        assert!(!winnable(&corridor, &[((2, 1), KeyColor::Red)]));
+        // the only key is behind the door it opens
+        assert!(!winnable(&corridor, &[((6, 1), KeyColor::Gold)]));
+    }
+
+    #[test]
+    fn keys_spent_in_the_wrong_order_are_not_a_dead_end() {
+        // one gold key, two gold doors: the one to the north only hides a closet
+        let rows = ["+--+--+--+", "|  |     |", "+L-+     |", "|s       |", "+--+--+L-+", "|g       |", "+--+--+--+"];
+        assert!(winnable(&maze(&rows), &[((6, 2), KeyColor::Gold)]));
+        // the red door is only reachable through the gold one
+        let chain = maze(&["+--+--+--+--+", "|s  L  R   g|", "+--+--+--+--+"]);
+        assert!(winnable(&chain, &[((2, 1), KeyColor::Gold), ((5, 1), KeyColor::Red)]));
+        assert!(!winnable(&chain, &[((2, 1), KeyColor::Gold), ((9, 1), KeyColor::Red)]));
+    }
+
+    #[test]
+    fn opened_doors_stay_open_but_keys_are_spent() {
+        // the red key is behind the gold door and the red door is back near the start
+        let rows = ["+--+--+--+", "|s  L    |", "+R-+--+--+", "|g       |", "+--+--+--+"];
+        assert!(winnable(&maze(&rows), &[((2, 1), KeyColor::Gold), ((6, 1), KeyColor::Red)]));
+        // one key can't open two doors, however often the player walks over it
+        let twice = maze(&["+--+--+--+--+", "|s  L  L   g|", "+--+--+--+--+"]);
+        assert!(!winnable(&twice, &[((2, 1), KeyColor::Gold)]));
+        assert!(winnable(&twice, &[((2, 1), KeyColor::Gold), ((5, 1), KeyColor::Gold)]));
+    }
+
+    #[test]
+    fn many_doors_to_choose_from_stay_cheap() {
+        // twenty closets off one corridor and ten keys, with the goal behind a
+        // red door and no red key: every way to spend the keys fails
+        let closets = "+L-".repeat(20) + "+--+";
+        let corridor = format!("|s{}R g|", " ".repeat(58));
+        let floor = "+--".repeat(21) + "+";
+        let rows = [closets.as_str(), corridor.as_str(), floor.as_str()];
+        let keys: Vec<_> = (0..10).map(|k| ((2 + k, 1), KeyColor::Gold)).collect();
+        assert!(!winnable(&maze(&rows), &keys));
+    }
+}
@@ -0,0 +1,107 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Built-in levels, relative to the working directory.
+pub const LEVELS_DIR: &str = "levels";
+/// Manifest file inside a pack directory.
+pub const MANIFEST: &str = "pack.txt";
+
+/// An ordered group of level files. Levels are played in `levels` order and the
+/// next pack starts after the last one.
+#[derive(Debug, Clone)]
+pub struct LevelPack {
+    pub name: String,
+    pub levels: Vec<PathBuf>,
+}
+
+/// Extra levels outside the game folder: `$MAZE_USER_LEVELS` if set, otherwise
+/// `~/.maze/levels`. Only used when the directory exists.
+pub fn user_levels_dir() -> Option<PathBuf> {
+    let dir = match env::var_os("MAZE_USER_LEVELS") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+            Path::new(&home).join(".maze").join("levels")
+        }
+    };
+    dir.is_dir().then_some(dir)
+}
+
+/// Level packs from the built-in directory, then the user directory.
+pub fn discover_packs() -> Vec<LevelPack> {
+    let mut packs = scan_dir(Path::new(LEVELS_DIR), "Levels");
+    if let Some(dir) = user_levels_dir() {
+        packs.extend(scan_dir(&dir, "User levels"));
+    }
+    packs
+}
+
+/// Every subdirectory is a pack, sorted by directory name. Level files lying
+/// directly in `dir` form one more pack called `loose_name`.
+pub fn scan_dir(dir: &Path, loose_name: &str) -> Vec<LevelPack> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        eprintln!("{}: no levels directory", dir.display());
+        return vec![];
+    };
+    let mut subdirs = Vec::new();
+    let mut loose = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if is_level_file(&path) {
+            loose.push(path);
+        }
+    }
+    subdirs.sort();
+    loose.sort();
+
+    let mut packs: Vec<LevelPack> = subdirs.iter().filter_map(|dir| load_pack(dir)).collect();
+    if !loose.is_empty() {
+        packs.push(LevelPack { name: loose_name.to_string(), levels: loose });
+    }
+    packs
+}
+
+/// Reads a pack directory. With a manifest, the levels are the files it lists, in
+/// that order; without one, every level file in the directory sorted by name.
+/// Empty packs are skipped.
+pub fn load_pack(dir: &Path) -> Option<LevelPack> {
+    let dir_name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let manifest = dir.join(MANIFEST);
+
+    let pack = match fs::read_to_string(&manifest) {
+        Ok(text) => {
+            let mut name = dir_name;
+            let mut levels = Vec::new();
+            for line in text.lines() {
+                let line = line.split('#').next().unwrap_or("").trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match line.split_once('=') {
+                    Some((key, value)) if key.trim() == "name" => name = value.trim().to_string(),
+                    Some((key, _)) => eprintln!("{}: unknown key `{}`", manifest.display(), key.trim()),
+                    // a listed file that is missing still shows up, and reports its error when played
+                    None => levels.push(dir.join(line)),
+                }
+            }
+            LevelPack { name, levels }
+        }
+        Err(_) => {
+            let mut levels: Vec<PathBuf> = fs::read_dir(dir)
+                .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| is_level_file(p)).collect())
+                .unwrap_or_default();
+            levels.sort();
+            LevelPack { name: dir_name, levels }
+        }
+    };
+    (!pack.levels.is_empty()).then_some(pack)
+}
+
+fn is_level_file(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|ext| ext == "txt")
+        && path.file_name().is_some_and(|name| name != MANIFEST)
+}
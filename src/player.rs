@@ -2,41 +2,30 @@ use raylib::prelude::*;
 use std::f32::consts::PI;
 
 use crate::maze::{Maze};
+use crate::sim::InputCommand;
 
+/// Walking speed, in blocks per second.
+pub const MOVE_SPEED: f32 = 2.5;
+/// Turning speed with the arrow keys, in radians per second.
+pub const TURN_SPEED: f32 = PI * 1.2;
+
+#[derive(Debug, Clone)]
 pub struct Player {
     pub pos: Vector2,
     pub a: f32,
     pub fov: f32,
     pub health: i32,
+    /// Multiplies `MOVE_SPEED`, above 1.0 during a speed boost
+    pub speed: f32,
 }
 
 impl Player {
     
-    pub fn process_events(&mut self, window: &mut RaylibHandle, maze: &Maze, block_size: f32) {
-        let move_speed: f32 = block_size / 25.0;
-        const ROTATION_SPEED: f32 = PI / 50.0;
-    
-        // Rotate player
-        if window.is_key_down(KeyboardKey::KEY_LEFT) {
-            self.a -= ROTATION_SPEED;
-        }
-        if window.is_key_down(KeyboardKey::KEY_RIGHT) {
-            self.a += ROTATION_SPEED;
-        }
-    
-        let screen_w = window.get_screen_width();
-        let screen_h = window.get_screen_height();
-    
-        let center = Vector2::new(
-            (screen_w / 2) as f32,
-            (screen_h / 2) as f32,
-        );
-        let mouse_pos = window.get_mouse_position(); 
-        let sensitivity: f32 = 0.003;              // tune to taste
-    
-            // Only use horizontal movement to rotate
-        self.a += (mouse_pos.x - center.x) * sensitivity;
-        window.set_mouse_position(center);
+    /// Turns and moves the player for `dt` seconds, sliding along walls.
+    pub fn apply_input(&mut self, input: &InputCommand, maze: &Maze, block_size: f32, dt: f32) {
+        let move_speed: f32 = MOVE_SPEED * self.speed * block_size * dt;
+
+        self.a += input.turn.clamp(-1.0, 1.0) * TURN_SPEED * dt + input.look;
     
         // Direction vector from angle
         let dir_x = self.a.cos();
@@ -44,78 +33,69 @@ impl Player {
     
         // Helper closure to test if a world position is inside a wall
         let can_walk_to = |x: f32, y: f32| -> bool {
-            if x < 0.0 || y < 0.0 {
-                return false;
-            }
-    
-            let i = (x / block_size) as usize;
-            let j = (y / block_size) as usize;
-    
-            if j >= maze.len() || i >= maze[0].len() {
-                return false;
-            }
-    
-            maze[j][i] == ' ' || maze[j][i] == 'g' || maze[j][i] == 's'
+            maze.cell_at(x, y, block_size).is_some_and(|cell| cell.is_walkable())
         };
-    
-        // Move forward
-        if window.is_key_down(KeyboardKey::KEY_UP) || window.is_key_down(KeyboardKey::KEY_W) {
-            // Try moving on X axis
-            let next_x = self.pos.x + dir_x * move_speed;
-            if can_walk_to(next_x, self.pos.y) {
-                self.pos.x = next_x;
-            }
-    
-            // Try moving on Y axis
-            let next_y = self.pos.y + dir_y * move_speed;
-            if can_walk_to(self.pos.x, next_y) {
-                self.pos.y = next_y;
-            }
-        }
-    
-        // Move backward
-        if window.is_key_down(KeyboardKey::KEY_DOWN) || window.is_key_down(KeyboardKey::KEY_S) {
-            // Try moving on X axis
-            let next_x = self.pos.x - dir_x * move_speed;
-            if can_walk_to(next_x, self.pos.y) {
-                self.pos.x = next_x;
-            }
-    
-            // Try moving on Y axis
-            let next_y = self.pos.y - dir_y * move_speed;
-            if can_walk_to(self.pos.x, next_y) {
-                self.pos.y = next_y;
-            }
-        }
-    
-        // Move Rightward
-        if window.is_key_down(KeyboardKey::KEY_D) {
-            // Try moving on X axis
-            let next_x = self.pos.x - dir_y * move_speed;
-            if can_walk_to(next_x, self.pos.y) {
-                self.pos.x = next_x;
-            }
-    
-            // Try moving on Y axis
-            let next_y = self.pos.y + dir_x * move_speed;
-            if can_walk_to(self.pos.x, next_y) {
-                self.pos.y = next_y;
-            }
+
+        // forward along the view direction, strafe along its right-hand normal
+        let forward = input.forward.clamp(-1.0, 1.0);
+        let strafe = input.strafe.clamp(-1.0, 1.0);
+        let dx = (dir_x * forward - dir_y * strafe) * move_speed;
+        let dy = (dir_y * forward + dir_x * strafe) * move_speed;
+    
+        // Try moving on X axis
+        let next_x = self.pos.x + dx;
+        if can_walk_to(next_x, self.pos.y) {
+            self.pos.x = next_x;
         }
     
-        // Move leftward
-        if window.is_key_down(KeyboardKey::KEY_A) {
-            // Try moving on X axis
-            let next_x = self.pos.x + dir_y * move_speed;
-            if can_walk_to(next_x, self.pos.y) {
-                self.pos.x = next_x;
-            }
-    
-            // Try moving on Y axis
-            let next_y = self.pos.y - dir_x * move_speed;
-            if can_walk_to(self.pos.x, next_y) {
-                self.pos.y = next_y;
-            }
+        // Try moving on Y axis
+        let next_y = self.pos.y + dy;
+        if can_walk_to(self.pos.x, next_y) {
+            self.pos.y = next_y;
         }
     }
-}
\ No newline at end of file
+}
+
+/// Translates keyboard and mouse state into an `InputCommand`. The mouse is
+/// recentred every call so its offset from the center is this frame's look.
+pub fn read_input(window: &mut RaylibHandle) -> InputCommand {
+    let mut input = InputCommand::default();
+
+    // Rotate player
+    if window.is_key_down(KeyboardKey::KEY_LEFT) {
+        input.turn -= 1.0;
+    }
+    if window.is_key_down(KeyboardKey::KEY_RIGHT) {
+        input.turn += 1.0;
+    }
+
+    let screen_w = window.get_screen_width();
+    let screen_h = window.get_screen_height();
+
+    let center = Vector2::new(
+        (screen_w / 2) as f32,
+        (screen_h / 2) as f32,
+    );
+    let mouse_pos = window.get_mouse_position(); 
+    let sensitivity: f32 = 0.003;              // tune to taste
+
+        // Only use horizontal movement to rotate
+    input.look += (mouse_pos.x - center.x) * sensitivity;
+    window.set_mouse_position(center);
+
+    if window.is_key_down(KeyboardKey::KEY_UP) || window.is_key_down(KeyboardKey::KEY_W) {
+        input.forward += 1.0;
+    }
+    if window.is_key_down(KeyboardKey::KEY_DOWN) || window.is_key_down(KeyboardKey::KEY_S) {
+        input.forward -= 1.0;
+    }
+    if window.is_key_down(KeyboardKey::KEY_D) {
+        input.strafe += 1.0;
+    }
+    if window.is_key_down(KeyboardKey::KEY_A) {
+        input.strafe -= 1.0;
+    }
+    // open doors
+    input.interact = window.is_key_pressed(KeyboardKey::KEY_E);
+    input
+}
@@ -0,0 +1,135 @@
+/// Size of the software framebuffer relative to the window. The frame is
+/// scaled up (or down) to the window when it is presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderScale {
+    /// Percent of the window size
+    Percent(u32),
+    /// Fixed size whatever the window, e.g. a retro 320x200
+    Fixed(u32, u32),
+}
+
+impl RenderScale {
+    pub const PRESETS: [RenderScale; 4] = [
+        RenderScale::Percent(100),
+        RenderScale::Percent(75),
+        RenderScale::Percent(50),
+        RenderScale::Fixed(320, 200),
+    ];
+
+    /// The preset after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = RenderScale::PRESETS.iter().position(|&s| s == self).unwrap_or(0);
+        RenderScale::PRESETS[(i + 1) % RenderScale::PRESETS.len()]
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            RenderScale::Percent(p) => format!("{}%", p),
+            RenderScale::Fixed(w, h) => format!("{}x{}", w, h),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Blocky pixels
+    Nearest,
+    /// Smoothed
+    Linear,
+}
+
+impl Filter {
+    pub fn toggled(self) -> Self {
+        match self {
+            Filter::Nearest => Filter::Linear,
+            Filter::Linear => Filter::Nearest,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Nearest => "nearest",
+            Filter::Linear => "linear",
+        }
+    }
+}
+
+/// How the 3D view is rendered and scaled to the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub scale: RenderScale,
+    pub filter: Filter,
+    /// Lower the resolution while frames take longer than `budget`
+    pub dynamic: bool,
+    /// Seconds per frame dynamic resolution aims for
+    pub budget: f32,
+    /// Extra factor from dynamic resolution, `MIN_DYNAMIC` to 1.0
+    pub dynamic_factor: f32,
+    /// Frame time, smoothed over the last frames
+    average_frame: f32,
+    /// Seconds until dynamic resolution may change again
+    cooldown: f32,
+}
+
+impl RenderSettings {
+    /// Lowest dynamic factor, so the view never gets unreadable.
+    pub const MIN_DYNAMIC: f32 = 0.4;
+    /// Seconds between dynamic resolution changes, so the view doesn't flicker
+    const DYNAMIC_INTERVAL: f32 = 0.5;
+
+    pub fn new() -> Self {
+        RenderSettings {
+            scale: RenderScale::Percent(100),
+            filter: Filter::Nearest,
+            dynamic: false,
+            budget: 1.0 / 60.0,
+            dynamic_factor: 1.0,
+            average_frame: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    /// Framebuffer size for a window of `window_w` x `window_h`, at least 1x1.
+    pub fn size(&self, window_w: i32, window_h: i32) -> (u32, u32) {
+        let (w, h) = match self.scale {
+            RenderScale::Percent(p) => (window_w as f32 * p as f32 / 100.0, window_h as f32 * p as f32 / 100.0),
+            RenderScale::Fixed(w, h) => (w as f32, h as f32),
+        };
+        let factor = if self.dynamic { self.dynamic_factor } else { 1.0 };
+        (((w * factor) as u32).max(1), ((h * factor) as u32).max(1))
+    }
+
+    /// Feeds the last frame time to dynamic resolution: it steps down when
+    /// frames are over budget and back up when there is room to spare.
+    pub fn observe_frame(&mut self, frame_time: f32) {
+        self.average_frame += (frame_time - self.average_frame) * 0.1;
+        self.cooldown -= frame_time;
+        if !self.dynamic || self.cooldown > 0.0 {
+            return;
+        }
+        let before = self.dynamic_factor;
+        if self.average_frame > self.budget * 1.1 {
+            self.dynamic_factor = (self.dynamic_factor - 0.1).max(RenderSettings::MIN_DYNAMIC);
+        } else if self.average_frame < self.budget * 0.8 {
+            self.dynamic_factor = (self.dynamic_factor + 0.05).min(1.0);
+        }
+        if self.dynamic_factor != before {
+            self.cooldown = RenderSettings::DYNAMIC_INTERVAL;
+        }
+    }
+
+    /// Short description for the HUD.
+    pub fn label(&self) -> String {
+        let mut label = format!("{} {}", self.scale.label(), self.filter.name());
+        if self.dynamic {
+            label += &format!(" dynamic {:.0}%", self.dynamic_factor * 100.0);
+        }
+        label
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings::new()
+    }
+}
@@ -0,0 +1,365 @@
+use std::f32::consts::PI;
+
+use raylib::prelude::Vector2;
+
+use crate::ai;
+use crate::animation::AnimName;
+use crate::enemies;
+use crate::items::{Effect, Item};
+use crate::level::Level;
+use crate::maze::{Cell, KeyColor, Maze, path};
+use crate::player::Player;
+use crate::rng::Rng;
+use crate::sprite::Enemy;
+
+/// What the player wants to do this step. The window front end fills it from
+/// keys and mouse; tests and tools can build it directly.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputCommand {
+    /// 1.0 forward, -1.0 backward
+    pub forward: f32,
+    /// 1.0 right, -1.0 left
+    pub strafe: f32,
+    /// Turn at `player::TURN_SPEED`: 1.0 clockwise on screen, -1.0 counter-clockwise
+    pub turn: f32,
+    /// Radians to turn right away, e.g. from the mouse; not scaled by time
+    pub look: f32,
+    /// Open the door in front of the player
+    pub interact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    /// Reached a goal cell
+    Won,
+    /// Out of health or out of time
+    Lost,
+}
+
+/// Length of one simulation step, in seconds. The game always advances in
+/// steps of this size however fast it renders; see `Stepper`.
+pub const TICK: f64 = 1.0 / 60.0;
+
+/// Seconds a door takes to slide fully open.
+pub const DOOR_OPEN_TIME: f32 = 1.0;
+
+/// Health the player starts each level with.
+pub const MAX_HEALTH: i32 = 5;
+
+/// Things that happened during a step that the front end may want to show or play.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepEvents {
+    pub player_hit: bool,
+    /// Sound of the enemy type that hit the player
+    pub hit_sound: Option<&'static str>,
+    /// Tried to open a locked door without a key of this color
+    pub door_locked: Option<KeyColor>,
+}
+
+/// Game state of one level in play: everything needed to advance the game,
+/// nothing that needs a window.
+pub struct World {
+    pub maze: Maze,
+    pub player: Player,
+    pub enemies: Vec<Enemy>,
+    /// Items still lying around
+    pub items: Vec<Item>,
+    pub block_size: f32,
+    pub max_health: i32,
+    /// Seconds simulated since the level started
+    pub time: f64,
+    /// Seconds left on levels with a time limit
+    pub time_left: Option<f64>,
+    pub outcome: Outcome,
+    /// Seconds of invulnerability after each hit
+    pub hit_cooldown: f64,
+    /// Keys picked up and not used yet, in pickup order
+    pub keys: Vec<KeyColor>,
+    pub score: u32,
+    /// Seconds left of the current speed boost
+    pub boost_left: f64,
+    /// Walking speed multiplier while `boost_left` runs
+    boost_factor: f32,
+    /// Doors still sliding open, as (column, row)
+    opening_doors: Vec<(usize, usize)>,
+    last_hit: f64,
+    rng: Rng,
+    /// Player and enemies as they were before the last step, for `interpolated`
+    prev_player: Player,
+    prev_enemy_pos: Vec<Vector2>,
+}
+
+impl World {
+    /// Sets up `level` with the player on the start cell. `seed` drives the enemies'
+    /// random moves, so the same seed and inputs replay the same game.
+    pub fn new(level: &Level, block_size: f32, max_health: i32, seed: u64) -> Self {
+        let center = |(i, j): (usize, usize)| {
+            Vector2::new((i as f32 + 0.5) * block_size, (j as f32 + 0.5) * block_size)
+        };
+        let start = level.maze.find(Cell::Start).unwrap_or((1, 1));
+        let player = Player {
+            pos: center(start),
+            a: level.start_angle,
+            fov: PI / 3.0,
+            health: max_health,
+            speed: 1.0,
+        };
+        let enemies = level.enemies
+            .iter()
+            .map(|spawn| {
+                // spawns are checked when the level is parsed
+                let kind = enemies::enemy_type(&spawn.kind).unwrap_or(enemies::default_type());
+                Enemy::new(center(spawn.cell), kind)
+            })
+            .collect::<Vec<_>>();
+        let items = level.items
+            .iter()
+            .map(|spawn| Item { kind: spawn.kind, pos: center(spawn.cell) })
+            .collect();
+        let hit_cooldown = 1.0;
+
+        World {
+            maze: level.maze.clone(),
+            prev_player: player.clone(),
+            prev_enemy_pos: enemies.iter().map(|e| e.pos).collect(),
+            player,
+            enemies,
+            items,
+            block_size,
+            max_health,
+            time: 0.0,
+            time_left: level.time_limit.map(f64::from),
+            outcome: Outcome::Playing,
+            hit_cooldown,
+            keys: Vec::new(),
+            score: 0,
+            boost_left: 0.0,
+            boost_factor: 1.0,
+            opening_doors: Vec::new(),
+            last_hit: -hit_cooldown,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Advances the game by `dt` seconds. Does nothing once the level is won or lost.
+    pub fn step(&mut self, input: &InputCommand, dt: f64) -> StepEvents {
+        let mut events = StepEvents::default();
+        if self.outcome != Outcome::Playing {
+            return events;
+        }
+        self.prev_player = self.player.clone();
+        self.prev_enemy_pos = self.enemies.iter().map(|e| e.pos).collect();
+        self.time += dt;
+        if let Some(time_left) = &mut self.time_left {
+            *time_left -= dt;
+        }
+
+        self.boost_left = (self.boost_left - dt).max(0.0);
+        self.player.speed = if self.boost_left > 0.0 { self.boost_factor } else { 1.0 };
+
+        if input.interact {
+            events.door_locked = self.interact().err();
+        }
+        self.update_doors(dt as f32);
+        self.player.apply_input(input, &self.maze, self.block_size, dt as f32);
+        self.pick_up_items();
+        self.update_enemies(dt as f32);
+        events.hit_sound = self.check_enemy_collisions();
+        events.player_hit = events.hit_sound.is_some();
+
+        if self.is_on_goal() {
+            self.outcome = Outcome::Won;
+        } else if self.player.health == 0 || self.time_left.is_some_and(|t| t <= 0.0) {
+            self.outcome = Outcome::Lost;
+        }
+        events
+    }
+
+    /// Starts opening the door right in front of the player, using up a key of
+    /// its color for a locked one. Door cells next to each other open as one door,
+    /// so a two cell wide gap only needs one key. Fails with the color of the
+    /// missing key if the door is locked and there is none.
+    fn interact(&mut self) -> Result<(), KeyColor> {
+        let reach = self.block_size * 0.9;
+        let x = self.player.pos.x + self.player.a.cos() * reach;
+        let y = self.player.pos.y + self.player.a.sin() * reach;
+        if x < 0.0 || y < 0.0 {
+            return Ok(());
+        }
+        let (i, j) = ((x / self.block_size) as usize, (y / self.block_size) as usize);
+        let cell = match self.maze.get(i, j) {
+            Some(cell) if cell.is_door() => cell,
+            _ => return Ok(()),
+        };
+        if let Cell::LockedDoor(color) = cell {
+            let key = self.keys.iter().position(|&k| k == color).ok_or(color)?;
+            self.keys.remove(key);
+        }
+
+        let mut stack = vec![(i, j)];
+        while let Some((i, j)) = stack.pop() {
+            self.maze.set(i, j, Cell::Door);
+            if !self.opening_doors.contains(&(i, j)) {
+                self.opening_doors.push((i, j));
+            }
+            for next in path::neighbors(&self.maze, (i, j)) {
+                if self.maze.get(next.0, next.1) == Some(cell) && !self.opening_doors.contains(&next) {
+                    stack.push(next);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Slides opening doors along; a door that is fully open becomes walkable.
+    fn update_doors(&mut self, dt: f32) {
+        let maze = &mut self.maze;
+        self.opening_doors.retain(|&(i, j)| {
+            let openness = maze.door_openness(i, j) + dt / DOOR_OPEN_TIME;
+            maze.set_door_openness(i, j, openness);
+            if openness >= 1.0 {
+                maze.set(i, j, Cell::OpenDoor);
+            }
+            openness < 1.0
+        });
+    }
+
+    /// Applies and removes the items the player walks over. Health stays on the
+    /// floor while the player is at full health.
+    fn pick_up_items(&mut self) {
+        let reach = self.block_size * 0.35;
+        let mut i = 0;
+        while i < self.items.len() {
+            let item = &self.items[i];
+            let close = (item.pos - self.player.pos).length() <= reach;
+            let taken = close && match item.kind.effect {
+                Effect::Heal(amount) => {
+                    let healed = self.player.health < self.max_health;
+                    self.player.health = (self.player.health + amount).min(self.max_health);
+                    healed
+                }
+                Effect::Key(color) => {
+                    self.keys.push(color);
+                    true
+                }
+                Effect::Score(points) => {
+                    self.score += points;
+                    true
+                }
+                Effect::SpeedBoost { factor, seconds } => {
+                    self.boost_factor = factor;
+                    self.boost_left = seconds as f64;
+                    true
+                }
+            };
+            if taken {
+                self.items.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Runs each enemy's AI, see `ai::update`, and its animation.
+    fn update_enemies(&mut self, dt: f32) {
+        for enemy in &mut self.enemies {
+            let before = enemy.pos;
+            ai::update(&mut enemy.brain, &mut enemy.pos, self.player.pos, &self.maze, self.block_size, dt, &mut self.rng);
+            let base = if enemy.pos != before { AnimName::Walk } else { AnimName::Idle };
+            enemy.animator.update(&enemy.kind.animations, base, dt);
+        }
+    }
+
+    /// Player and enemies blended between the previous and the current step,
+    /// `alpha` 0.0 being the previous one. Rendering with these keeps motion
+    /// smooth when frames don't line up with steps.
+    pub fn interpolated(&self, alpha: f32) -> (Player, Vec<Enemy>) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let lerp = |a: Vector2, b: Vector2| a + (b - a) * alpha;
+
+        let mut player = self.player.clone();
+        player.pos = lerp(self.prev_player.pos, self.player.pos);
+        player.a = self.prev_player.a + (self.player.a - self.prev_player.a) * alpha;
+
+        let enemies = self.enemies
+            .iter()
+            .zip(&self.prev_enemy_pos)
+            .map(|(enemy, &prev)| Enemy { pos: lerp(prev, enemy.pos), ..enemy.clone() })
+            .collect();
+        (player, enemies)
+    }
+
+    /// Player wins if standing on a goal cell.
+    fn is_on_goal(&self) -> bool {
+        self.maze.cell_at(self.player.pos.x, self.player.pos.y, self.block_size) == Some(Cell::Goal)
+    }
+
+    /// Player loses health when within an enemy's hit radius, at most once per
+    /// `hit_cooldown`. Returns the hit sound of the enemy that hit.
+    fn check_enemy_collisions(&mut self) -> Option<&'static str> {
+        let mut hit = None;
+
+        for enemy in &mut self.enemies {
+            let damage_distance = enemy.kind.hit_radius * self.block_size;
+            let dx = enemy.pos.x - self.player.pos.x;
+            let dy = enemy.pos.y - self.player.pos.y;
+            let dist_sq = dx * dx + dy * dy;
+            if dist_sq <= damage_distance * damage_distance && self.time - self.last_hit >= self.hit_cooldown {
+                self.last_hit = self.time;
+                self.player.health = (self.player.health - enemy.kind.damage).max(0);
+                hit = Some(enemy.kind.sound);
+                enemy.animator.play(AnimName::Attack);
+            }
+        }
+        hit
+    }
+}
+
+/// Fixed-timestep driver: collects real frame time and runs the world in whole
+/// `TICK` steps, keeping the remainder for the next frame.
+#[derive(Debug, Clone, Default)]
+pub struct Stepper {
+    accumulator: f64,
+    /// Mouse look not yet handed to a step
+    pending_look: f32,
+    /// Interact pressed but not yet handed to a step
+    pending_interact: bool,
+}
+
+impl Stepper {
+    /// Longest frame we catch up on; after a stall the game slows down instead
+    /// of running hundreds of steps at once.
+    const MAX_FRAME: f64 = 0.25;
+
+    pub fn new() -> Self {
+        Stepper::default()
+    }
+
+    /// Runs as many steps as `frame_dt` allows and merges their events.
+    pub fn advance(&mut self, world: &mut World, input: &InputCommand, frame_dt: f64) -> StepEvents {
+        self.accumulator += frame_dt.clamp(0.0, Stepper::MAX_FRAME);
+        self.pending_look += input.look;
+        self.pending_interact |= input.interact;
+
+        let mut events = StepEvents::default();
+        while self.accumulator >= TICK {
+            // mouse look is a distance, not a rate, and a key press happens once:
+            // hand both to the first step only
+            let step_input = InputCommand { look: self.pending_look, interact: self.pending_interact, ..*input };
+            self.pending_look = 0.0;
+            self.pending_interact = false;
+            let step = world.step(&step_input, TICK);
+            events.player_hit |= step.player_hit;
+            events.hit_sound = step.hit_sound.or(events.hit_sound);
+            events.door_locked = step.door_locked.or(events.door_locked);
+            self.accumulator -= TICK;
+        }
+        events
+    }
+
+    /// How far between the last step and the next one we are, 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK) as f32
+    }
+}
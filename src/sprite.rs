@@ -1,23 +1,109 @@
 use raylib::prelude::*;
+use crate::ai::Brain;
+use crate::animation::{Animator, Frame};
+use crate::enemies::EnemyType;
 use crate::framebuffer::Framebuffer;
+use crate::maze::Maze;
 use crate::player::Player;
 use crate::textures::TextureManager;
 use std::{f32::consts::PI};
 
+#[derive(Debug, Clone)]
 pub struct Enemy {
     pub pos: Vector2,
-    pub texture_key: char
+    pub kind: &'static EnemyType,
+    pub health: i32,
+    pub brain: Brain,
+    pub animator: Animator,
 }
 
-const TRANSPARENT_COLOR: Color = Color::new(152, 0, 136, 255);
+impl Enemy {
+    pub fn new(pos: Vector2, kind: &'static EnemyType) -> Self {
+        Enemy { pos, kind, health: kind.health, brain: Brain::new(kind.ai), animator: Animator::new() }
+    }
+
+    /// How the enemy looks to someone standing at `viewer`.
+    pub fn sprite(&self, viewer: Vector2) -> Sprite {
+        let to_viewer = viewer - self.pos;
+        let view_angle = to_viewer.y.atan2(to_viewer.x) - self.brain.facing;
+        let frame = self.animator
+            .frame(&self.kind.animations, view_angle)
+            .unwrap_or(Frame::new('e'));
+        Sprite { pos: self.pos, frame, color: self.kind.tint, scale: 1.0 }
+    }
+}
+
+/// Anything drawn as a billboard facing the player.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub pos: Vector2,
+    pub frame: Frame,
+    /// Tints the texture; drawn as a flat disc of this color when the texture is missing
+    pub color: Color,
+    /// Size relative to an enemy; smaller sprites stand on the floor
+    pub scale: f32,
+}
+
+/// Height of a full size sprite in blocks. Projected like the walls' height
+/// (see `WALL_HEIGHT` in main.rs), so the two keep their proportions at any
+/// render resolution.
+const SPRITE_HEIGHT: f32 = 0.54;
+
+/// Screen height in pixels of a full size sprite `depth` away from the camera plane.
+fn projected_size(framebuffer: &Framebuffer, player: &Player, block_size: f32, depth: f32) -> f32 {
+    let distance_to_projection_plane = framebuffer.width as f32 / 2.0 / (player.fov / 2.0).tan();
+    block_size * SPRITE_HEIGHT / depth * distance_to_projection_plane
+}
+
+/// Distances between which sprites are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteCutoffs {
+    /// Closer sprites are drawn as if they were this far, so they stay partly
+    /// on screen instead of blowing up or vanishing
+    pub near: f32,
+    /// Nothing in the level can be farther than this
+    pub far: f32,
+}
+
+impl SpriteCutoffs {
+    /// `near` is where a full size sprite covers the whole field of view; `far`
+    /// is the diagonal of the level.
+    pub fn new(framebuffer: &Framebuffer, player: &Player, maze: &Maze, block_size: f32) -> Self {
+        // projected_size is inversely proportional to depth
+        let near = projected_size(framebuffer, player, block_size, 1.0) / framebuffer.width as f32;
+        let far = (maze.width() as f32).hypot(maze.height() as f32) * block_size;
+        SpriteCutoffs { near, far }
+    }
+}
+
+/// Draws sprites back to front, hidden behind walls using the distance of each
+/// screen column from the ray pass (see `render_world`).
+pub fn draw_sprites(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    sprites: &mut [Sprite],
+    column_depths: &[f32],
+    cutoffs: &SpriteCutoffs,
+    block_size: f32,
+    texture_manager: &TextureManager,
+) {
+    let distance = |sprite: &Sprite| (sprite.pos - player.pos).length();
+    sprites.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+    for sprite in sprites.iter() {
+        draw_sprite(framebuffer, player, sprite, column_depths, cutoffs, block_size, texture_manager);
+    }
+}
 
 pub fn draw_sprite(
     framebuffer: &mut Framebuffer,
     player: &Player,
-    enemy: &Enemy,
+    sprite: &Sprite,
+    column_depths: &[f32],
+    cutoffs: &SpriteCutoffs,
+    block_size: f32,
     texture_manager: &TextureManager
 ) {
-    let sprite_a = (enemy.pos.y - player.pos.y).atan2(enemy.pos.x - player.pos.x);
+    let sprite_a = (sprite.pos.y - player.pos.y).atan2(sprite.pos.x - player.pos.x);
     let mut angle_diff = sprite_a - player.a;
     while angle_diff > PI {
         angle_diff -= 2.0 * PI;
@@ -26,44 +112,95 @@ pub fn draw_sprite(
         angle_diff += 2.0 * PI;
     }
 
-    if angle_diff.abs() > player.fov / 2.0 {
-        return;
-    }
-
-    let sprite_d = ((player.pos.x - enemy.pos.x).powi(2) + (player.pos.y - enemy.pos.y).powi(2)).sqrt();
-
-    // near plane           far plane
-    if sprite_d < 50.0 || sprite_d > 1000.0 {
+    let sprite_d = (sprite.pos - player.pos).length();
+    // distance along the view direction, the same measure the walls use
+    let depth = sprite_d * angle_diff.cos();
+    if depth <= 0.0 || sprite_d > cutoffs.far {
         return;
     }
 
     let screen_height = framebuffer.height as f32;
     let screen_width = framebuffer.width as f32;
 
-    let sprite_size = (screen_height / sprite_d) * 70.0;
+    let full_size = projected_size(framebuffer, player, block_size, depth.max(cutoffs.near));
+    let sprite_size = full_size * sprite.scale;
     let screen_x = ((angle_diff / player.fov) + 0.5) * screen_width;
+    // keep the bottom where a full size sprite would end
+    let center_y = screen_height / 2.0 + (full_size - sprite_size) / 2.0;
 
-    let start_x = (screen_x - sprite_size / 2.0).max(0.0) as usize;
-    let start_y = (screen_height / 2.0 - sprite_size / 2.0).max(0.0) as usize;
-    let sprite_size_usize = sprite_size as usize;
-    let end_x = (start_x + sprite_size_usize).min(framebuffer.width as usize);
-    let end_y = (start_y + sprite_size_usize).min(framebuffer.height as usize);
+    // unclipped corner, so the texture lines up when the sprite is partly off screen
+    let left = screen_x - sprite_size / 2.0;
+    let top = center_y - sprite_size / 2.0;
+    let start_x = left.max(0.0) as usize;
+    let start_y = top.max(0.0) as usize;
+    let end_x = (left + sprite_size).clamp(0.0, screen_width) as usize;
+    let end_y = (top + sprite_size).clamp(0.0, screen_height) as usize;
+
+    if start_x >= end_x || start_y >= end_y {
+        return;
+    }
 
-    if let Some(image) = texture_manager.images.get(&enemy.texture_key) {
-        let tex_w = image.width as usize;
-        let tex_h = image.height as usize;
-        for x in start_x..end_x {
+    // columns where a wall is in front of the sprite are skipped whole
+    let visible = |x: usize| column_depths.get(x).is_none_or(|&wall| depth < wall);
+
+    let frame = sprite.frame;
+    if texture_manager.images.contains_key(&frame.key) {
+        for x in (start_x..end_x).filter(|&x| visible(x)) {
+            let u = (x as f32 - left) / sprite_size;
             for y in start_y..end_y {
-                let tx = ((x - start_x) * tex_w / sprite_size_usize) as u32;
-                let ty = ((y - start_y) * tex_h / sprite_size_usize) as u32;
-
-                let color = texture_manager.get_pixel_color(enemy.texture_key, tx, ty);
-                
-                if color != TRANSPARENT_COLOR {
-                    framebuffer.set_current_color(color);
-                    framebuffer.set_pixel(x as u32, y as u32, sprite_d);
+                let v = (y as f32 - top) / sprite_size;
+                let (su, sv) = frame.sample(u, v);
+                let Some(color) = texture_manager.sample(frame.key, frame.index as usize, su, sv) else { continue };
+
+                // the manifest's key color is loaded as see-through
+                if color.a > 0 {
+                    framebuffer.set_current_color(tint(color, sprite.color));
+                    framebuffer.set_pixel(x as u32, y as u32, depth);
                 }
             }
         }
+    } else {
+        // no texture: a flat disc
+        let radius = sprite_size / 2.0;
+        framebuffer.set_current_color(sprite.color);
+        for x in (start_x..end_x).filter(|&x| visible(x)) {
+            for y in start_y..end_y {
+                let dx = x as f32 + 0.5 - screen_x;
+                let dy = y as f32 + 0.5 - center_y;
+                if dx * dx + dy * dy <= radius * radius {
+                    framebuffer.set_pixel(x as u32, y as u32, depth);
+                }
+            }
+        }
+    }
+}
+
+fn tint(color: Color, tint: Color) -> Color {
+    let mul = |a: u8, b: u8| (a as u16 * b as u16 / 255) as u8;
+    Color::new(mul(color.r, tint.r), mul(color.g, tint.g), mul(color.b, tint.b), color.a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::AnimName;
+    use crate::enemies::enemy_type;
+
+    #[test]
+    fn a_hurt_enemy_shows_its_hurt_frames_then_goes_back_to_idle() {
+        let pumpkin = enemy_type("pumpkin").unwrap();
+        let mut enemy = Enemy::new(Vector2::new(0.0, 0.0), pumpkin);
+        // seen from straight in front, facing east
+        let viewer = Vector2::new(100.0, 0.0);
+        assert_eq!(enemy.sprite(viewer).frame, Frame::new('e'));
+
+        enemy.animator.play(AnimName::Hurt);
+        assert_eq!(enemy.sprite(viewer).frame, Frame::new('b'));
+        enemy.animator.update(&pumpkin.animations, AnimName::Idle, 0.2);
+        assert_eq!(enemy.sprite(viewer).frame, Frame::new('b').turned(2));
+
+        enemy.animator.update(&pumpkin.animations, AnimName::Idle, 0.2);
+        assert_eq!(enemy.animator.current, AnimName::Idle);
+        assert_eq!(enemy.sprite(viewer).frame, Frame::new('e'));
     }
-}
\ No newline at end of file
+}
@@ -2,37 +2,177 @@
 
 use raylib::prelude::*;
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::slice;
 
+use crate::level::strip_comment;
+
+/// Lists every texture the game uses, see `parse_manifest`.
+pub const TEXTURE_MANIFEST: &str = "assets/textures.txt";
+
+/// Side of the checkerboard used in place of a texture that failed to load.
+const PLACEHOLDER_SIZE: i32 = 64;
+
+/// One line of the texture manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureEntry {
+    pub id: char,
+    /// Relative to the manifest's directory
+    pub path: String,
+    /// Pixels of this color are see-through in sprites
+    pub key: Option<Color>,
+    /// Sprite sheet layout, (columns, rows); (1, 1) for a single image
+    pub frames: (u32, u32),
+    /// A missing file is fine: the renderer falls back to flat colors
+    pub optional: bool,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Manifest { path: String, source: io::Error },
+    /// Bad manifest line, counted from 1
+    Parse { path: String, line: usize, message: String },
+    /// A texture file that could not be loaded
+    Missing { id: char, path: String },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Manifest { path, source } => write!(f, "{}: {}", path, source),
+            TextureError::Parse { path, line, message } => write!(f, "{}: line {}: {}", path, line, message),
+            TextureError::Missing { id, path } => write!(f, "{}: could not load texture '{}'", path, id),
+        }
+    }
+}
+
+/// Parses a texture manifest: one texture per line, with `#` comments as in
+/// level headers (see `level::strip_comment`), so a file may be named `#1.png`.
+///
+/// ```text
+/// e   carved_pumpkin.png  key=152,0,136    # id, file, options
+/// w   walk.png            key=152,0,136 frames=4x1
+/// _   floor.png           optional
+/// ```
+///
+/// `key` is the see-through color of a sprite, `frames` splits a sprite sheet
+/// into columns x rows, and `optional` textures may be missing.
+pub fn parse_manifest(text: &str) -> Result<Vec<TextureEntry>, (usize, String)> {
+    let mut entries: Vec<TextureEntry> = Vec::new();
+    for (n, raw) in text.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let (Some(id), Some(path)) = (words.next(), words.next()) else {
+            return Err((n + 1, "expected `<id> <file> [options]`".to_string()));
+        };
+        let mut chars = id.chars();
+        let (Some(id), None) = (chars.next(), chars.next()) else {
+            return Err((n + 1, format!("id `{}` must be a single character", id)));
+        };
+        if entries.iter().any(|e| e.id == id) {
+            return Err((n + 1, format!("id '{}' is listed twice", id)));
+        }
+
+        let mut entry = TextureEntry { id, path: path.to_string(), key: None, frames: (1, 1), optional: false };
+        for option in words {
+            match option.split_once('=') {
+                Some(("key", rgb)) => {
+                    let parts: Vec<Option<u8>> = rgb.split(',').map(|c| c.parse().ok()).collect();
+                    match parts.as_slice() {
+                        [Some(r), Some(g), Some(b)] => entry.key = Some(Color::new(*r, *g, *b, 255)),
+                        _ => return Err((n + 1, format!("key `{}` must be r,g,b from 0 to 255", rgb))),
+                    }
+                }
+                Some(("frames", layout)) => {
+                    let size = layout.split_once('x').and_then(|(c, r)| Some((c.parse().ok()?, r.parse().ok()?)));
+                    match size {
+                        Some((columns, rows)) if columns > 0 && rows > 0 => entry.frames = (columns, rows),
+                        _ => return Err((n + 1, format!("frames `{}` must be <columns>x<rows>", layout))),
+                    }
+                }
+                None if option == "optional" => entry.optional = true,
+                _ => return Err((n + 1, format!("unknown option `{}`", option))),
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
 pub struct TextureManager {
     pub images: HashMap<char, Image>,       // Store images for pixel access
     textures: HashMap<char, Texture2D>, // Store GPU textures for rendering
+    /// Sprite sheet layouts, (columns, rows)
+    frames: HashMap<char, (u32, u32)>,
+    /// Textures that failed to load and were replaced by a checkerboard
+    pub missing: Vec<TextureError>,
 }
 
 impl TextureManager {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
-        let mut images = HashMap::new();
-        let mut textures = HashMap::new();
-
-        // Map characters to texture file paths
-        let texture_files = vec![
-            //('+', "assets/dirt.png"),
-            //('-', "assets/stone.png"),
-            //('|', "assets/wall1.png"),
-            //('g', "assets/wall5.png"),
-            ('#', "assets/pumpkinblur.png"),
-            ('e', "assets/carved_pumpkin.png"),
-        ];
-
-        for (ch, path) in texture_files {
-            let mut image = Image::load_image(path).expect(&format!("Failed to load image {}", path));
-            image.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8);
-            let texture = rl.load_texture(thread, path).expect(&format!("Failed to load texture {}", path));
-            images.insert(ch, image);
-            textures.insert(ch, texture);
+    /// No textures at all: walls use flat colors and sprites are drawn as discs.
+    pub fn empty() -> Self {
+        TextureManager { images: HashMap::new(), textures: HashMap::new(), frames: HashMap::new(), missing: Vec::new() }
+    }
+
+    /// Loads everything listed in `manifest` and uploads it to the GPU. Fails
+    /// only if the manifest itself can't be read, see `load_images`.
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, manifest: &str) -> Result<Self, TextureError> {
+        let mut manager = TextureManager::load_images(manifest)?;
+        manager.upload(rl, thread);
+        Ok(manager)
+    }
+
+    /// Loads the images listed in `manifest` without touching the GPU, so it
+    /// works before (or without) a window. A texture file that fails to load
+    /// gets a checkerboard placeholder and is listed in `missing`, unless it is optional.
+    pub fn load_images(manifest: &str) -> Result<Self, TextureError> {
+        let text = fs::read_to_string(manifest).map_err(|source| TextureError::Manifest {
+            path: manifest.to_string(),
+            source,
+        })?;
+        let entries = parse_manifest(&text).map_err(|(line, message)| TextureError::Parse {
+            path: manifest.to_string(),
+            line,
+            message,
+        })?;
+        let dir = Path::new(manifest).parent().unwrap_or(Path::new(""));
+
+        let mut manager = TextureManager::empty();
+        for entry in entries {
+            let path = dir.join(&entry.path).to_string_lossy().into_owned();
+            let mut image = match load_image(entry.id, &path) {
+                Ok(image) => image,
+                Err(_) if entry.optional => continue,
+                Err(err) => {
+                    manager.missing.push(err);
+                    placeholder()
+                }
+            };
+            if let Some(key) = entry.key {
+                image.color_replace(key, Color::BLANK);
+            }
+            manager.images.insert(entry.id, image);
+            manager.frames.insert(entry.id, entry.frames);
         }
+        Ok(manager)
+    }
 
-        TextureManager { images, textures }
+    /// Creates a GPU texture for every loaded image.
+    pub fn upload(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+        for (&id, image) in &self.images {
+            match rl.load_texture_from_image(thread, image) {
+                Ok(texture) => {
+                    self.textures.insert(id, texture);
+                }
+                Err(err) => eprintln!("texture '{}': {}", id, err),
+            }
+        }
     }
 
     pub fn get_pixel_color(&self, ch: char, tx: u32, ty: u32) -> Color {
@@ -45,17 +185,41 @@ impl TextureManager {
         }
     }
 
+    /// Color at (`u`, `v`), both from 0.0 to 1.0, in frame `index` of a sprite
+    /// sheet, counting left to right then top to bottom. None without such a texture.
+    pub fn sample(&self, ch: char, index: usize, u: f32, v: f32) -> Option<Color> {
+        let image = self.images.get(&ch)?;
+        let (columns, rows) = self.frames.get(&ch).copied().unwrap_or((1, 1));
+        let index = index as u32 % (columns * rows);
+        let frame_w = (image.width as u32 / columns).max(1);
+        let frame_h = (image.height as u32 / rows).max(1);
+        let x = (index % columns) * frame_w + ((u.clamp(0.0, 1.0) * frame_w as f32) as u32).min(frame_w - 1);
+        let y = (index / columns) * frame_h + ((v.clamp(0.0, 1.0) * frame_h as f32) as u32).min(frame_h - 1);
+        Some(get_pixel_color(image, x as i32, y as i32))
+    }
+
     pub fn get_texture(&self, ch: char) -> Option<&Texture2D> {
         self.textures.get(&ch)
     }
-    pub fn rotate_images(&mut self) {
-        for image in self.images.values_mut() {
-            image.rotate_ccw();
-        }
-    }
 }
 
-fn get_pixel_color(image: &Image, x: i32, y: i32) -> Color {
+/// Loads an image file as RGBA.
+pub fn load_image(id: char, path: &str) -> Result<Image, TextureError> {
+    let mut image = Image::load_image(path).map_err(|_| TextureError::Missing { id, path: path.to_string() })?;
+    image.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8);
+    Ok(image)
+}
+
+/// Magenta and black checkerboard, hard to miss on screen.
+pub fn placeholder() -> Image {
+    let checks = PLACEHOLDER_SIZE / 8;
+    let mut image = Image::gen_image_checked(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, checks, checks, Color::MAGENTA, Color::BLACK);
+    image.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8);
+    image
+}
+
+/// Color of the pixel at (`x`, `y`), white outside the image.
+pub fn get_pixel_color(image: &Image, x: i32, y: i32) -> Color {
     let width = image.width as usize;
     let height = image.height as usize;
 
@@ -80,3 +244,23 @@ fn get_pixel_color(image: &Image, x: i32, y: i32) -> Color {
         Color::new(data[idx], data[idx + 1], data[idx + 2], data[idx + 3])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_comments_need_whitespace_before_the_hash() {
+        let text = "# textures\n\
+                    e  pumpkin#2.png  key=152,0,136   # the face\n\
+                    \n\
+                    w  walk.png frames=4x1 optional#not a comment\n";
+        assert_eq!(parse_manifest(text).unwrap_err(), (4, "unknown option `optional#not`".to_string()));
+
+        let entries = parse_manifest(&text.replace("optional#not a comment", "optional #a comment")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].id, entries[0].path.as_str()), ('e', "pumpkin#2.png"));
+        assert_eq!(entries[0].key, Some(Color::new(152, 0, 136, 255)));
+        assert_eq!((entries[1].frames, entries[1].optional), ((4, 1), true));
+    }
+}